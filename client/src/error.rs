@@ -12,6 +12,7 @@ pub enum Error {
     SpriteLoad,
     MapLoad,
     DataLoad,
+    SoundLoad,
     Rm(core_compat::error::Error),
     Io(io::Error),
     Utf8(Utf8Error),
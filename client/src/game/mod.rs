@@ -1,18 +1,19 @@
 mod scene;
 mod character;
 
-use std::path::{ PathBuf };
+use std::path::Path;
+use std::rc::Rc;
 
-use core_compat::entity::sprite_type::SpriteType;
-use core_compat::entity::rmd_type::RmdType;
+use core_compat::repository::game_data::GameData;
+use core_compat::repository::vfs::Vfs;
+use geometry::isometric::Projection;
 
 use crate::sdl::Sdl;
 
 use crate::resource_manager::map_manager::MapManager;
-use crate::resource_manager::data_manager::DataManager;
 use crate::resource_manager::sprite_manager::SpriteManager;
+use crate::resource_manager::sound_manager::SoundManager;
 use crate::resource_manager::list_manager::ListManager;
-use crate::resource_manager::list_manager::ListType;
 
 use crate::error::Error;
 
@@ -31,35 +32,35 @@ pub struct State {
 pub struct Game {
     // game and input state
     pub window: (i32, i32),
+    // where map tiles are on the screen
+    pub projection: Projection,
     pub state: State,
     pub input: input::Input,
     // data managers
     pub map_manager: MapManager,
-    pub data_manager: DataManager,
+    // follows the map tiles to their sprites
+    pub game_data: GameData,
     pub sprite_manager: SpriteManager,
+    pub sound_manager: SoundManager,
     pub list_manager: ListManager,
 }
 
 impl Game {
     pub fn new() -> Game {
 
-        // Construct paths
-        let mut path_data = PathBuf::new();
-        let mut path_map = PathBuf::new();
-        let mut path_sprite = PathBuf::new();
+        // Game files are read from the data folder, files in mods replace them
+        let vfs = Rc::new(Vfs::new(Path::new("data")).with_overlay(Path::new("mods")));
 
-        path_data.push("data/DATAs");
-        path_map.push("data/DATAs/Map");
-        path_sprite.push("data/RLEs");
-
-        let map_manager = MapManager::new(&path_map);
-        let data_manager = DataManager::new(&path_data);
-        let sprite_manager = SpriteManager::new(&path_sprite);
-        let list_manager = ListManager::new(&path_sprite).unwrap();
+        let map_manager = MapManager::new(vfs.clone());
+        let game_data = GameData::with_vfs(vfs.clone());
+        let sprite_manager = SpriteManager::new(vfs.clone());
+        let sound_manager = SoundManager::new(vfs.clone());
+        let list_manager = ListManager::new(vfs).unwrap();
 
         Game {
             // window state
             window: (800, 600),
+            projection: Projection::default(),
 
             // game and input state
             state: State {
@@ -71,8 +72,9 @@ impl Game {
 
             // data managers
             map_manager,
-            data_manager,
+            game_data,
             sprite_manager,
+            sound_manager,
             list_manager,
         }
     }
@@ -133,96 +135,28 @@ impl Game {
     }
 
     pub fn load_map(&mut self, map_number: usize, sdl: &mut Sdl) -> Result<(), Error> {
-        // load the map data
-        self.map_manager.load_map(map_number)?;
+        // load the map data and follow the tiles to their sprites
+        self.map_manager.load_map(map_number, &mut self.game_data)?;
         let map = self.map_manager.get_map(map_number)?;
-        // debug
-        let mut tile_x = 0;
-        let mut _tile_y = 0;
-        let tile_stride = map.size_x();
-        // load the tile data
-        let obj_list = self.list_manager.get_list(ListType::Object).unwrap();
-        let tle_list = self.list_manager.get_list(ListType::Tile).unwrap();
-        for map_tile in map.tiles().iter() {
-            // load references to the data files
-            // -- map tile objects
-            let obj_entry = map_tile.obj_rmd_entry;
-            if obj_entry.file() != 0 {
-                // -- load entry data
-                let file = obj_entry.file() as usize;
-                let index = obj_entry.index() as usize;
-                if let Ok(rmd) = self.data_manager.get_data(RmdType::Object,file) {
-                    match rmd.get_entry(index) {
-                        Some(entry) => {
-                            // -- load images
-                            for img in entry.images() {
-                                // debug
-                                // if (img.render_z != 2) && (img.render_z != 0) {
-                                //     println!("({:3}, {:3})   dest_x: {:3}, dest_y: {:3}, z: {}",
-                                //              tile_x, tile_y, img.dest_x, img.dest_y, img.render_z);
-                                // }
-
-                                for id in img.image_id.iter() {
-                                    let idx = *id as usize;
-                                    let item = obj_list.get_item(idx).unwrap();
-                                    let _sprite =
-                                        self.sprite_manager
-                                            .get_sprite_entry(&item.entry,
-                                                              SpriteType::Object,
-                                                              sdl)?;
-                                }
-                            }
-                        },
-                        None => {
-                            println!("failed to get rmd entry for map object");
-                            println!("file:  {}", file);
-                            println!("index: {}", index);
-                        }
-                    }
-
-                    // -- load animations
-                    for _ani in rmd.animations() {
-                        // todo
-                    }
-                } else {
-                    continue;
-                }
-            }
-            // -- map tile sprites
-            let tle_entry = map_tile.tle_rmd_entry;
-            if tle_entry.file() != 0 {
-                let file = tle_entry.file() as usize;
-                let index = tle_entry.index() as usize;
-                if let Ok(rmd) = self.data_manager.get_data(RmdType::Tile, file) {
-                    match rmd.get_entry(index) {
-                        Some(entry) => {
-                            for img in entry.images() {
-                                for id in img.image_id.iter() {
-                                    let item = tle_list.get_item(*id as usize).unwrap();
-                                    let _sprite = self.sprite_manager.get_sprite_entry(&item.entry, SpriteType::Tile, sdl)?;
-                                }
-                            }
-                        },
-                        None => {
-                            println!("failed to get rmd entry for map tile");
-                            println!("file:  {}", file);
-                            println!("index: {}", index);
-                        }
-                    }
-                } else {
-                    continue;
-                }
+        let map_sprites = self.map_manager.get_map_sprites(map_number)?;
+        for &((tile_x, tile_y), ref e) in map_sprites.errors() {
+            println!("failed to resolve map tile ({}, {}): {}", tile_x, tile_y, e);
+        }
+        // load the sprites of every tile
+        for ((tile_x, tile_y), _map_tile) in map.enumerate_tiles() {
+            for img in map_sprites.tile_at(tile_x, tile_y) {
+                // debug
+                // if (img.layer.raw() != 2) && (img.layer.raw() != 0) {
+                //     println!("({:3}, {:3})   dest_x: {:3}, dest_y: {:3}, z: {}",
+                //              tile_x, tile_y, img.dest_x, img.dest_y, img.layer.raw());
+                // }
+
+                let _sprite = self.sprite_manager.get_tile_sprite(img, &mut self.game_data, sdl)?;
             }
+            // TODO: load the object animations
 
             // debugging
-            {
-                tile_x += 1;
-                if tile_x >= tile_stride {
-                    tile_x = 0;
-                    _tile_y += 1;
-                }
-                // println!("map_tile.collision: 0x{:2x}", map_tile.collision);
-            }
+            // println!("map_tile.collision: 0x{:2x}", _map_tile.collision);
         }
 
         println!("loaded map: {}", map_number);
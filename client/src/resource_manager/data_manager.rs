@@ -1,9 +1,5 @@
 
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::path::Path;
-use std::fs::File;
-use std::io::Read;
 use std::rc::Rc;
 
 use crate::error::Error;
@@ -11,9 +7,10 @@ use crate::error::Error;
 use core_compat::entity::rmd_type::RmdType;
 use core_compat::entity::rmd::Rmd;
 use core_compat::parser::rmd::parse_rmd;
+use core_compat::repository::vfs::Vfs;
 
 pub struct DataManager {
-    data_path: PathBuf,
+    vfs: Rc<Vfs>,
     tle_map: HashMap<usize, Rc<Rmd>>,
     obj_map: HashMap<usize, Rc<Rmd>>,
     ico_map: HashMap<usize, Rc<Rmd>>,
@@ -22,9 +19,9 @@ pub struct DataManager {
 }
 
 impl DataManager {
-    pub fn new(path: &Path) -> DataManager {
+    pub fn new(vfs: Rc<Vfs>) -> DataManager {
         DataManager {
-            data_path: path.into(),
+            vfs,
             bul_map: HashMap::new(),
             ico_map: HashMap::new(),
             chr_map: HashMap::new(),
@@ -62,7 +59,6 @@ impl DataManager {
 
     fn load_rmd(&mut self, kind: RmdType, number: usize) -> Result<(), Error> {
         // generate correct path for the map
-        let mut path: PathBuf = self.data_path.clone();
         let dir_str = match kind {
             RmdType::Tile => { "Tle" },
             RmdType::Object => { "Obj" },
@@ -77,19 +73,16 @@ impl DataManager {
             RmdType::Character => { format!("chr{:05}.rmd", number) },
             RmdType::Bullet => { format!("bul{:05}.rmd", number) },
         };
-        path.push(dir_str);
-        path.push(&map_str);
+        let path = format!("DATAs/{}/{}", dir_str, map_str);
         // load data from file
         // println!("trying to open: {:?}", &path);
-        let mut file = match File::open(&path) {
-            Ok(f) => f,
+        let data = match self.vfs.read(&path) {
+            Ok(data) => data,
             Err(e) => {
-                println!("failed to open RMD file: {:?}", &path);
-                return Err(Error::Io(e));
+                println!("failed to open RMD file: {}", e);
+                return Err(Error::Rm(e));
             }
         };
-        let mut data = Vec::<u8>::new();
-        file.read_to_end(&mut data)?;
         // parse map and insert into resource_manager
         let rmd = parse_rmd(kind, &data)?;
         match kind {
@@ -114,11 +107,12 @@ impl DataManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
 
     #[test]
     fn test_load_tle_rmd_00001() {
-        let data_path = Path::new("../data/DATAs/");
-        let mut rmd = DataManager::new(&data_path);
+        let vfs = Rc::new(Vfs::new(Path::new("../data/")));
+        let mut rmd = DataManager::new(vfs);
         let _rmd_no = 1usize;
         let _data = rmd.get_data(RmdType::Tile, 1).unwrap();
     }
@@ -1,12 +1,10 @@
-use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use std::io::Read;
-use std::fs::File;
 use std::rc::Rc;
 
 use core_compat::entity::list::List;
 // use core_compat::entity::list_item::ListItem;
-use core_compat::parser::lst::parse_lst;
+use core_compat::parser::lst::parse_lst_lenient;
+use core_compat::repository::vfs::Vfs;
 
 use crate::error::Error;
 
@@ -31,37 +29,39 @@ pub enum ListType {
     Etc,
 }
 
-static LIST_PATHS: [(&'static ListType, &'static str); 5] = [
-    (&ListType::Bullet, "bul.lst"),
-    (&ListType::Icon, "ico.lst"),
+static LIST_PATHS: [(&'static ListType, &'static str); 6] = [
+    // type              | file
+    (&ListType::Bullet,    "bul.lst"),
+    (&ListType::Icon,      "ico.lst"),
     (&ListType::Interface, "int.lst"),
-    (&ListType::Tile, "tle.lst"),
-    (&ListType::Object, "obj.lst"),
+    (&ListType::Sound,     "snd.lst"),
+    (&ListType::Tile,      "tle.lst"),
+    (&ListType::Object,    "obj.lst"),
 ];
 
 pub struct ListManager {
-    rle_path: PathBuf,
+    vfs: Rc<Vfs>,
     list_map: HashMap<ListType, Rc<List>>,
 }
 
 impl ListManager {
-    pub fn new(rle_path: &Path) -> Result<ListManager, Error> {
+    pub fn new(vfs: Rc<Vfs>) -> Result<ListManager, Error> {
         let mut lm = ListManager {
-            rle_path: rle_path.into(),
+            vfs,
             list_map: HashMap::new(),
         };
         for entry in LIST_PATHS.iter() {
             let (kind, path) = *entry;
             // create path
-            let mut next_path = lm.rle_path.clone();
-            next_path.push(path);
+            let next_path = format!("RLEs/{}", path);
             // open and read file
             println!("Loading list file: {:?}", next_path);
-            let mut file = File::open(&next_path)?;
-            let mut data = Vec::<u8>::new();
-            file.read_to_end(&mut data)?;
+            let data = lm.vfs.read(&next_path)?;
             // parse data
-            let list_file = parse_lst(&data, false)?;
+            let (list_file, fallbacks) = parse_lst_lenient(&data)?;
+            for fallback in fallbacks {
+                println!("Recovered list file {:?}: {:?}", next_path, fallback);
+            }
             // save it in map
             lm.list_map.insert(*kind, Rc::new(list_file));
         }
@@ -1,26 +1,28 @@
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::fs::File;
-use std::io::Read;
 use std::rc::Rc;
 
 use core_compat::entity::map::Map;
+use core_compat::entity::map_sprites::MapSprites;
 // use core_compat::entity::map_tile::MapTile;
 use core_compat::parser::rmm::parse_rmm;
+use core_compat::repository::game_data::GameData;
+use core_compat::repository::vfs::Vfs;
 
 use crate::error::Error;
 
 pub struct MapManager {
-    data_path: PathBuf,
+    vfs: Rc<Vfs>,
     maps: HashMap<usize, Rc<Map>>,
+    sprites: HashMap<usize, Rc<MapSprites>>,
 }
 
 impl MapManager {
 
-    pub fn new(data_path: &Path) -> MapManager {
+    pub fn new(vfs: Rc<Vfs>) -> MapManager {
         MapManager {
-            data_path: data_path.into(),
+            vfs,
             maps: HashMap::new(),
+            sprites: HashMap::new(),
         }
     }
 
@@ -32,23 +34,30 @@ impl MapManager {
         Ok(map)
     }
 
-    pub fn load_map(&mut self, number: usize) -> Result<(), Error> {
+    /// The sprites of every tile of the map, resolved when it was loaded
+    pub fn get_map_sprites(&self, number: usize) -> Result<Rc<MapSprites>, Error> {
+        let sprites = match self.sprites.get(&number) {
+            Some(sprites) => sprites.clone(),
+            None => return Err(Error::MapLoad),
+        };
+        Ok(sprites)
+    }
+
+    pub fn load_map(&mut self, number: usize, game_data: &mut GameData) -> Result<(), Error> {
         // generate correct path for the map
-        let map_str = format!("Map{:05}.rmm", number);
-        let mut path: PathBuf = self.data_path.clone();
-        path.push(&map_str);
+        let map_str = format!("DATAs/Map/Map{:05}.rmm", number);
         // load data from file
-        let mut file = match File::open(&path) {
-            Ok(f) => f,
+        let data = match self.vfs.read(&map_str) {
+            Ok(data) => data,
             Err(e) => {
-                println!("Failed to open map file: {:?}", &path);
-                return Err(Error::Io(e));
+                println!("Failed to open map file: {}", e);
+                return Err(Error::Rm(e));
             }
         };
-        let mut data = Vec::<u8>::new();
-        file.read_to_end(&mut data)?;
         // parse map and insert into resource_manager
         let map = parse_rmm(&data)?;
+        // follow the tiles to their sprites once instead of on every frame
+        self.sprites.insert(number, Rc::new(game_data.resolve_map(&map)));
         self.maps.insert(number, Rc::new(map));
         println!("Loaded map: {}", &map_str);
         Ok(())
@@ -62,14 +71,16 @@ impl MapManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
 
     #[test]
     fn test_load_map00001() {
         // load the map files
-        let map_data_path = Path::new("../data/DATAs/Map/");
-        let mut map_man = MapManager::new(&map_data_path);
+        let vfs = Rc::new(Vfs::new(Path::new("../data/")));
+        let mut game_data = GameData::with_vfs(vfs.clone());
+        let mut map_man = MapManager::new(vfs);
         let map_no = 1usize;
-        map_man.load_map(map_no).unwrap();
+        map_man.load_map(map_no, &mut game_data).unwrap();
         let map = map_man.maps.get(&1).unwrap();
         assert_eq!(map.number(), 1);
         assert_eq!((map.size_x() * map.size_y()) as usize, map.tile_count());
@@ -1,4 +1,5 @@
 pub mod data_manager;
 pub mod map_manager;
 pub mod sprite_manager;
+pub mod sound_manager;
 pub mod list_manager;
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use core_compat::entity::entry::Entry;
+use core_compat::entity::sound::Sound;
+use core_compat::parser::rms::{parse_rms, sound_path};
+use core_compat::repository::vfs::Vfs;
+
+use crate::error::Error;
+
+/// Loads the sounds pointed to by the `snd.lst` items, see `sound_path`.
+pub struct SoundManager {
+    vfs: Rc<Vfs>,
+    sounds: HashMap<u32, Rc<Sound>>,
+}
+
+impl SoundManager {
+    pub fn new(vfs: Rc<Vfs>) -> SoundManager {
+        SoundManager {
+            vfs,
+            sounds: HashMap::new(),
+        }
+    }
+
+    pub fn get_sound(&mut self, entry: &Entry) -> Result<Rc<Sound>, Error> {
+        if let Some(sound) = self.sounds.get(&entry.file()) {
+            return Ok(sound.clone());
+        }
+        self.load_sound(entry)?;
+        match self.sounds.get(&entry.file()) {
+            Some(sound) => Ok(sound.clone()),
+            None => Err(Error::SoundLoad),
+        }
+    }
+
+    fn load_sound(&mut self, entry: &Entry) -> Result<(), Error> {
+        // generate correct path for the sound
+        let path = sound_path(entry);
+        // load data from file
+        let data = match self.vfs.read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("Failed to open sound file: {}", e);
+                return Err(Error::Rm(e));
+            }
+        };
+        // parse sound and insert into resource_manager
+        let sound = parse_rms(&data)?;
+        self.sounds.insert(entry.file(), Rc::new(sound));
+        Ok(())
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.sounds.len()
+    }
+}
@@ -1,16 +1,18 @@
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::fs::File;
-use std::io::Read;
 
 use sdl2;
 
 // use core_compat::entity::resource_file::ResourceFile;
 use core_compat::entity::entry::Entry;
+use core_compat::entity::pixel_format::PixelFormat;
+use core_compat::entity::resource::Resource;
 use core_compat::entity::sprite::Sprite;
 use core_compat::entity::sprite_type::SpriteType::{self, Bullet, Character, Interface, Icon, Tile, Object};
-use core_compat::parser::rle::parse_rle;
+use core_compat::entity::tile_sprite::TileSprite;
+use core_compat::parser::rle::RleReader;
+use core_compat::repository::game_data::GameData;
+use core_compat::repository::vfs::Vfs;
 
 use crate::error::Error;
 use crate::sdl::Sdl;
@@ -21,25 +23,27 @@ pub struct SpriteEntry {
 }
 
 pub struct SpriteManager {
-    db_path: PathBuf,
+    vfs: Rc<Vfs>,
     bul_map: HashMap<Entry, Rc<SpriteEntry>>,
     ico_map: HashMap<Entry, Rc<SpriteEntry>>,
     chr_map: HashMap<Entry, Rc<SpriteEntry>>,
     obj_map: HashMap<Entry, Rc<SpriteEntry>>,
     tle_map: HashMap<Entry, Rc<SpriteEntry>>,
     int_map: HashMap<Entry, Rc<SpriteEntry>>,
+    readers: HashMap<(SpriteType, u32), RleReader>,
 }
 
 impl SpriteManager {
-    pub fn new(db_path: &Path) -> SpriteManager {
+    pub fn new(vfs: Rc<Vfs>) -> SpriteManager {
         SpriteManager {
-            db_path: db_path.into(),
+            vfs,
             bul_map: HashMap::new(),
             ico_map: HashMap::new(),
             chr_map: HashMap::new(),
             obj_map: HashMap::new(),
             tle_map: HashMap::new(),
             int_map: HashMap::new(),
+            readers: HashMap::new(),
         }
     }
 
@@ -59,7 +63,7 @@ impl SpriteManager {
             }
 
             if need_load {
-                self.load_sprite(req_entry.file(), sprite_type, sdl)?;
+                self.load_sprite(req_entry, sprite_type, sdl)?;
             }
         }
 
@@ -70,6 +74,26 @@ impl SpriteManager {
         }
     }
 
+    /// Same as `get_sprite_entry` for a sprite of a map tile, which is decoded by `game_data`
+    pub fn get_tile_sprite(
+        &mut self,
+        tile_sprite: &TileSprite,
+        game_data: &mut GameData,
+        sdl: &mut Sdl
+    ) -> Result<&SpriteEntry, Error> {
+        let sprite_type = tile_sprite.sprite_type;
+        if self.req_sprite(&tile_sprite.rle_entry, sprite_type).is_none() {
+            let resource = game_data.resource_as(tile_sprite, PixelFormat::R5g6b5)?;
+            self.insert_sprite(sprite_type, tile_sprite.rle_entry.file(), resource, sdl)?;
+        }
+
+        if let Some(entry) = self.req_sprite(&tile_sprite.rle_entry, sprite_type) {
+            Ok(entry)
+        } else {
+            Err(Error::SpriteLoad)
+        }
+    }
+
     fn req_sprite(
         &self,
         entry: &Entry,
@@ -91,10 +115,86 @@ impl SpriteManager {
 
     fn load_sprite(
         &mut self,
-        number: u32,
+        req_entry: &Entry,
         sprite_type: SpriteType,
         sdl: &mut Sdl
     ) -> Result<(), Error> {
+        let number = req_entry.file();
+        if !self.readers.contains_key(&(sprite_type, number)) {
+            let reader = self.load_reader(number, sprite_type)?;
+            self.readers.insert((sprite_type, number), reader);
+        }
+        // only the requested resource is decoded, the rest of the file stays untouched
+        let reader = &self.readers[&(sprite_type, number)];
+        let resource = match reader.read_resource_as(req_entry.index(), PixelFormat::R5g6b5)? {
+            Some(resource) => resource,
+            None => return Err(Error::SpriteLoad),
+        };
+        self.insert_sprite(sprite_type, number, resource, sdl)
+    }
+
+    /// Uploads a decoded r5g6b5 resource of the RLE file `number` as a texture
+    fn insert_sprite(
+        &mut self,
+        sprite_type: SpriteType,
+        number: u32,
+        resource: Resource,
+        sdl: &mut Sdl
+    ) -> Result<(), Error> {
+        let entry = Entry::new(number, resource.index());
+        let sprite = Sprite {
+            class: sprite_type,
+            rle_entry: entry,
+            x_dim: resource.width,
+            y_dim: resource.height,
+            x_off: resource.offset_x,
+            y_off: resource.offset_y,
+            pixel_format: resource.pixel_format,
+            image_raw: resource.image_raw,
+            mask: resource.mask,
+        };
+
+        // fully covered sprites (most tiles) are uploaded as is, the others need the mask as
+        // an alpha channel; 32 bits keep all of the colour, 16 bit formats with alpha have
+        // one bit less of green
+        let is_opaque = sprite.mask.iter().all(|&m| m != 0);
+        let format = if is_opaque {
+            sdl2::pixels::PixelFormatEnum::RGB565
+        } else {
+            sdl2::pixels::PixelFormatEnum::ARGB8888
+        };
+        let mut texture = sdl.texture_creator.create_texture(
+            Some(format),
+            sdl2::render::TextureAccess::Static,
+            resource.width as u32,
+            resource.height as u32)?;
+        texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+        if is_opaque {
+            texture.update(None, &sprite.image_raw, resource.width as usize * 2).unwrap();
+        } else {
+            let image = format_argb8888(&sprite.image_raw, &sprite.mask);
+            texture.update(None, &image, resource.width as usize * 4).unwrap();
+        }
+
+        let sprite_entry = Rc::new(SpriteEntry { sprite, texture });
+
+        match sprite_type {
+            Bullet    => { self.bul_map.insert(entry, sprite_entry); },
+            Icon      => { self.ico_map.insert(entry, sprite_entry); },
+            Character => { self.chr_map.insert(entry, sprite_entry); },
+            Object    => { self.obj_map.insert(entry, sprite_entry); },
+            Tile      => { self.tle_map.insert(entry, sprite_entry); },
+            Interface => { self.int_map.insert(entry, sprite_entry); },
+        }
+        Ok(())
+    }
+
+    /// Opens an RLE file and reads its offset table, the images are decoded in `load_sprite`
+    fn load_reader(
+        &self,
+        number: u32,
+        sprite_type: SpriteType
+    ) -> Result<RleReader, Error> {
         // generate correct path for the sprite
         let folder_str = match sprite_type {
             Bullet    => {"Bul"},
@@ -112,54 +212,20 @@ impl SpriteManager {
             Tile      => format!("tle{:05}.rle", number),
             Interface => format!("int{:05}.rle", number),
         };
-        let mut path: PathBuf = self.db_path.clone();
-        path.push(folder_str);
-        path.push(file_str);
+        let path = format!("RLEs/{}/{}", folder_str, file_str);
         // load data
-        let mut file = match File::open(&path) {
-            Ok(file) => file,
+        let data = match self.vfs.read(&path) {
+            Ok(data) => data,
             Err(e) => {
-                println!("Failed to load sprite file: {:?}", &path);
-                return Err(Error::Io(e))
+                println!("Failed to load sprite file: {}", e);
+                return Err(Error::Rm(e))
             }
         };
-        let mut data = Vec::<u8>::new();
-        file.read_to_end(&mut data)?;
-        // parse rle file and insert into resource_manager
-        let resource_file = parse_rle(number, &data)?;
-        for resource in resource_file.resources {
-            let entry = Entry::new(number, resource.index() );
-            let sprite = Sprite {
-                class: sprite_type,
-                rle_entry: entry,
-                x_dim: resource.width,
-                y_dim: resource.height,
-                x_off: resource.offset_x,
-                y_off: resource.offset_y,
-                image_raw: resource.image_raw,
-            };
-
-            let mut texture = sdl.texture_creator.create_texture(
-                Some(sdl2::pixels::PixelFormatEnum::ABGR8888),
-                sdl2::render::TextureAccess::Static,
-                resource.width as u32,
-                resource.height as u32)?;
-            texture.set_blend_mode(sdl2::render::BlendMode::Blend);
-            let pitch = resource.width as usize * 4;
-            texture.update(None, &sprite.image_raw, pitch).unwrap();
-
-            let sprite_entry = Rc::new(SpriteEntry { sprite, texture });
-
-            match sprite_type {
-                Bullet    => { self.bul_map.insert(entry, sprite_entry); },
-                Icon      => { self.ico_map.insert(entry, sprite_entry); },
-                Character => { self.chr_map.insert(entry, sprite_entry); },
-                Object    => { self.obj_map.insert(entry, sprite_entry); },
-                Tile      => { self.tle_map.insert(entry, sprite_entry); },
-                Interface => { self.int_map.insert(entry, sprite_entry); },
-            }
-        }
-        Ok(())
+        // only the header and offset table are parsed here
+        let mut reader = RleReader::new(number, data)?;
+        // some objects and interface parts are drawn over a magenta background
+        reader.set_color_key(sprite_type.color_key());
+        Ok(reader)
     }
 
     pub fn get_count(&self) -> usize {
@@ -171,3 +237,18 @@ impl SpriteManager {
         + self.int_map.len()
     }
 }
+
+/// Expands little endian r5g6b5 pixels and their coverage mask into native endian a8r8g8b8.
+/// The channels are widened by repeating their high bits, the same as SDL does for the
+/// opaque RGB565 textures, so both kinds of sprites show the same colours.
+fn format_argb8888(image: &[u8], mask: &[u8]) -> Vec<u8> {
+    let mut out = Vec::<u8>::with_capacity(image.len() * 2);
+    for (px, &m) in image.chunks(2).zip(mask.iter()) {
+        let c = px[0] as u32 | (px[1] as u32) << 8;
+        let (r, g, b) = (c >> 11, (c >> 5) & 0x3F, c & 0x1F);
+        let alpha = if m != 0 { 0xFF } else { 0x0 };
+        let c = alpha << 24 | (r << 3 | r >> 2) << 16 | (g << 2 | g >> 4) << 8 | (b << 3 | b >> 2);
+        out.extend_from_slice(&c.to_ne_bytes());
+    }
+    out
+}
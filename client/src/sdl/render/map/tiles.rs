@@ -1,78 +1,70 @@
 use crate::sdl::Sdl;
 use crate::game::Game;
-use crate::resource_manager::list_manager::ListType;
 use geometry::rectangle::Rectangle;
 use geometry::point::Point;
-use core_compat::entity::rmd_type::RmdType;
 use sdl2;
 use sdl2::rect::Rect;
-use crate::sdl::render::map::next_tile;
 use core_compat::entity::sprite_type::SpriteType;
+use core_compat::entity::collision::Collision;
 
 pub fn tiles(sdl: &mut Sdl, game: &mut Game) {
-    let tle_list = game.list_manager.get_list(ListType::Tile).unwrap();
     let map = game.map_manager.get_map(game.state.map).unwrap();
-    let tile_stride = map.size_x() as i32;
-    let tile_height = 24i32;
-    let tile_width = 48i32;
-    let mut tile_x = 0i32;
-    let mut tile_y = 0i32;
+    let map_sprites = game.map_manager.get_map_sprites(game.state.map).unwrap();
+    let projection = game.projection;
 
     let view_bounds = Rectangle::new_from_points(
         (-100 - game.state.map_off.0, -100 - game.state.map_off.1),
         (100 + game.window.0, 100 + game.window.1),
     );
+    // only the tiles in view, clamped to the map
+    let visible_tiles = projection.visible_tiles(&view_bounds)
+        .clamp(map.size_x() as i32, map.size_y() as i32);
+    let visible_width = (visible_tiles.max.x - visible_tiles.min.x + 1).max(0) as u32;
+    let visible_height = (visible_tiles.max.y - visible_tiles.min.y + 1).max(0) as u32;
+    let mouse_tile = projection.screen_to_tile(&Point::new(
+        game.input.mouse_x - game.state.map_off.0,
+        game.input.mouse_y - game.state.map_off.1,
+    ));
 
-    for map_tile in map.tiles().iter() {
-        let tile_offset = Point::new(tile_x * tile_width, tile_y * tile_height);
-        let mouse_offset = Point::new(game.input.mouse_x, game.input.mouse_y);
-
-        // skip tiles which out out of view
-        if view_bounds.contains_point(&tile_offset) == false {
-            next_tile(&mut tile_x, &mut tile_y, tile_stride);
-            continue;
-        }
+    let visible_region = map.region(
+        visible_tiles.min.x as u32,
+        visible_tiles.min.y as u32,
+        visible_width,
+        visible_height,
+    );
+    for ((tile_x, tile_y), map_tile) in visible_region {
+        let tile = Point::new(tile_x as i32, tile_y as i32);
+        let tile_offset = projection.tile_to_screen(&tile);
 
         // draw map tile
-        let tle_entry = map_tile.tle_rmd_entry;
-        if tle_entry.file() != 0 {
-            let file = tle_entry.file() as usize;
-            let index = tle_entry.index() as usize;
-            if let Ok(rmd) = game.data_manager.get_data(RmdType::Tile, file) {
-                if let Some(entry) = rmd.get_entry(index) {
-                    for img in entry.images() {
-                        for id in img.image_id.iter() {
-                            let item = tle_list.get_item(*id as usize).unwrap();
-                            let sprite = game.sprite_manager.get_sprite_entry(&item.entry, SpriteType::Tile, sdl).unwrap();
-                            let _w = (img.source_x2 - img.source_x1) as u32;
-                            let _h = (img.source_y2 - img.source_y1) as u32;
-                            let src_rect = Rect::new(img.source_x1, img.source_y1, _w, _h);
-                            let mut dst_rect = Rect::new(0, 0, tile_width as u32, tile_height as u32);
-                            dst_rect.offset(tile_offset.x, tile_offset.y);
-                            dst_rect.offset(game.state.map_off.0, game.state.map_off.1);
+        let sprites = map_sprites.tile_at(tile_x, tile_y);
+        for img in sprites.iter().filter(|img| img.sprite_type == SpriteType::Tile) {
+            let sprite = match game.sprite_manager.get_tile_sprite(img, &mut game.game_data, sdl) {
+                Ok(sprite) => sprite,
+                Err(_) => continue,
+            };
+            let _w = (img.source_x2 - img.source_x1) as u32;
+            let _h = (img.source_y2 - img.source_y1) as u32;
+            let src_rect = Rect::new(img.source_x1, img.source_y1, _w, _h);
+            let mut dst_rect = Rect::new(0, 0, projection.tile_width() as u32, projection.tile_height() as u32);
+            dst_rect.offset(tile_offset.x, tile_offset.y);
+            dst_rect.offset(game.state.map_off.0, game.state.map_off.1);
 
-                            // render
-                            let _ = sdl.canvas.copy(&sprite.texture, src_rect, dst_rect);
+            // render
+            let _ = sdl.canvas.copy(&sprite.texture, src_rect, dst_rect);
 
-                            // debug render
-                            {
-                                let _rect = Rectangle::new_from_points((dst_rect.x(), dst_rect.y()), (dst_rect.width() as i32, dst_rect.height() as i32));
-                                if _rect.contains_point(&mouse_offset) {
-                                    match map_tile.collision {
-                                        0 => sdl.canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 10, 10)),
-                                        _ => sdl.canvas.set_draw_color(sdl2::pixels::Color::RGB(10, 255, 10)),
-                                    }
-                                    let _ = sdl.canvas.draw_rect(dst_rect);
-                                }
-                            }
-                        }
+            // debug render
+            {
+                if tile == mouse_tile {
+                    match map_tile.collision_kind() {
+                        Collision::None => sdl.canvas.set_draw_color(sdl2::pixels::Color::RGB(10, 255, 10)),
+                        Collision::Full => sdl.canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 10, 10)),
+                        _ => sdl.canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 10)),
                     }
+                    let _ = sdl.canvas.draw_rect(dst_rect);
                 }
             }
         }
-
-        // update tile positions
-        next_tile(&mut tile_x, &mut tile_y, tile_stride);
     }
 }
 
@@ -0,0 +1,58 @@
+/// One of the two spots of a map tile a character can stand on
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SubSpot {
+    LeftTop,
+    RightBottom,
+}
+
+/// The collision setting of a map tile, decoded from the tile's collision byte.
+///
+/// The byte is `setting * 24 + n` where a non zero `n` marks an odd object index (see
+/// `parser::rmm`), so the setting is the byte divided by 24.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Collision {
+    /// Both spots can be walked on
+    None,
+    /// Neither spot can be walked on
+    Full,
+    /// The left top spot is blocked
+    LeftTop,
+    /// The right bottom spot is blocked
+    RightBottom,
+    /// A setting that isn't understood yet; treated as blocked
+    Unknown(u8),
+}
+
+impl Collision {
+    pub fn from_raw(raw: u8) -> Collision {
+        match raw / 24 {
+            0 => Collision::None,
+            1 => Collision::Full,
+            2 => Collision::LeftTop,
+            3 => Collision::RightBottom,
+            setting => Collision::Unknown(setting),
+        }
+    }
+
+    /// The setting part of the collision byte, the inverse of `from_raw`
+    pub fn setting(&self) -> u8 {
+        match *self {
+            Collision::None => 0,
+            Collision::Full => 1,
+            Collision::LeftTop => 2,
+            Collision::RightBottom => 3,
+            Collision::Unknown(setting) => setting,
+        }
+    }
+
+    pub fn is_walkable(&self, spot: SubSpot) -> bool {
+        matches!(
+            (*self, spot),
+            (Collision::None, _)
+                | (Collision::LeftTop, SubSpot::RightBottom)
+                | (Collision::RightBottom, SubSpot::LeftTop)
+        )
+    }
+}
@@ -0,0 +1,32 @@
+/// A colour that marks transparent pixels in an RLE image, see `SpriteType::color_key` for
+/// the one each kind of sprite is read with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColorKey {
+    /// Every painted pixel is opaque
+    #[default]
+    Off,
+    /// Exact magenta (r5g6b5 `0xF81F`), used as the alpha colour by some object and
+    /// interface sprites
+    Magenta,
+    /// A caller supplied r5g6b5 colour
+    Custom(u16),
+}
+
+impl ColorKey {
+    pub const MAGENTA: u16 = 0xF81F;
+
+    /// The keyed r5g6b5 colour, if any
+    pub fn key(&self) -> Option<u16> {
+        match *self {
+            ColorKey::Off => None,
+            ColorKey::Magenta => Some(ColorKey::MAGENTA),
+            ColorKey::Custom(key) => Some(key),
+        }
+    }
+
+    /// Whether a pixel of colour `c` (r5g6b5) should be transparent
+    pub fn is_keyed(&self, c: u16) -> bool {
+        self.key() == Some(c)
+    }
+}
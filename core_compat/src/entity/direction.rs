@@ -0,0 +1,47 @@
+/// The eight directions of the isometric view, as seen on the screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction {
+    /// Clockwise, starting at `Up`
+    pub const ALL: [Direction; 8] = [
+        Direction::Up,
+        Direction::UpRight,
+        Direction::Right,
+        Direction::DownRight,
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::Left,
+        Direction::UpLeft,
+    ];
+
+    /// The step in map tiles. The tiles are diamonds with the x axis running down to the
+    /// right and the y axis down to the left (see `geometry::isometric`), so the screen
+    /// diagonals move along one axis and straight up, down, left or right moves along both.
+    pub fn offset(&self) -> (i32, i32) {
+        match *self {
+            Direction::Up => (-1, -1),
+            Direction::UpRight => (0, -1),
+            Direction::Right => (1, -1),
+            Direction::DownRight => (1, 0),
+            Direction::Down => (1, 1),
+            Direction::DownLeft => (0, 1),
+            Direction::Left => (-1, 1),
+            Direction::UpLeft => (-1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        Direction::ALL[(*self as usize + 4) % 8]
+    }
+}
@@ -0,0 +1,36 @@
+/// How the sprites of an `RmdImage` are blended, decoded from its `draw_type` value.
+///
+/// Experimental until `test_rmd_game_draw_types` in the rmd parser has been run on the game's
+/// Chr, Obj and Bul files: the numbering only follows the old `enum { Shadow, skill, normal }`
+/// note on the field. That test prints the counts and fails on values that aren't named here. Unnamed values are kept as `Unknown` and drawn like `Normal`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DrawType {
+    /// 0: the shadow of a character or object
+    Shadow,
+    /// 1: skill and spell effects
+    Skill,
+    /// 2: drawn as is
+    Normal,
+    Unknown(i32),
+}
+
+impl DrawType {
+    pub fn from_raw(raw: i32) -> DrawType {
+        match raw {
+            0 => DrawType::Shadow,
+            1 => DrawType::Skill,
+            2 => DrawType::Normal,
+            raw => DrawType::Unknown(raw),
+        }
+    }
+
+    pub fn raw(&self) -> i32 {
+        match *self {
+            DrawType::Shadow => 0,
+            DrawType::Skill => 1,
+            DrawType::Normal => 2,
+            DrawType::Unknown(raw) => raw,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Event {
     pub number: u16,
     pub left: u32,   // c1_x
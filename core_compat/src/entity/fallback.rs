@@ -0,0 +1,22 @@
+use crate::entity::list_version::ListVersion;
+
+/// A deviation from what a file declares that a lenient parser made to read it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Fallback {
+    /// The file identifier didn't match, the content was read anyway
+    IgnoredIdentifier { found: String },
+    /// The list items were read with another layout than the advertised version
+    ListLayout { advertised: String, layout: ListVersion },
+    /// Only `read` of the `declared` entries (list items, map tiles, resource
+    /// offsets) fit in the file
+    TruncatedEntries { declared: u32, read: u32 },
+    /// `len` bytes after the last entry, starting at `offset`, were ignored
+    TrailingBytes { offset: u64, len: u64 },
+    /// Resources that point outside of the file or fail to decode were left out
+    SkippedResources { indices: Vec<u32> },
+    /// The map size didn't fit the tiles in the file, `used` is the size that does
+    MapSize { declared: (u32, u32), used: (u32, u32) },
+    /// Tiles with bits set that the format doesn't allow; the bits are kept in `unknown_1`
+    UnexpectedTileBits { count: u32 },
+}
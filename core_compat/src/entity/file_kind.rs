@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// The file formats handled by the parsers
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FileKind {
+    Lst,
+    Mid,
+    Rle,
+    Rmd,
+    Rmi,
+    Rmm,
+    Rms,
+}
+
+impl FileKind {
+    /// The kind named by a file extension, regardless of its case
+    pub fn from_extension(extension: &str) -> Option<FileKind> {
+        match extension.to_lowercase().as_str() {
+            "lst" => Some(FileKind::Lst),
+            "mid" => Some(FileKind::Mid),
+            "rle" => Some(FileKind::Rle),
+            "rmd" => Some(FileKind::Rmd),
+            "rmi" => Some(FileKind::Rmi),
+            "rmm" => Some(FileKind::Rmm),
+            "rms" => Some(FileKind::Rms),
+            _ => None,
+        }
+    }
+
+    /// The file extension, without the dot
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            FileKind::Lst => "lst",
+            FileKind::Mid => "mid",
+            FileKind::Rle => "rle",
+            FileKind::Rmd => "rmd",
+            FileKind::Rmi => "rmi",
+            FileKind::Rmm => "rmm",
+            FileKind::Rms => "rms",
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.extension().to_uppercase())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::entity::file_kind::FileKind;
+use crate::entity::list::List;
+use crate::entity::map::Map;
+use crate::entity::midi::Midi;
+use crate::entity::resource_file::ResourceFile;
+use crate::entity::rmd::Rmd;
+use crate::entity::rmi::Rmi;
+use crate::entity::sound::Sound;
+
+/// Any of the parsed game files, as returned by `parser::detect::parse_any`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameFile {
+    Lst(List),
+    Mid(Midi),
+    Rle(ResourceFile),
+    Rmd(Rmd),
+    Rmi(Rmi),
+    Rmm(Map),
+    Rms(Sound),
+}
+
+impl GameFile {
+    pub fn kind(&self) -> FileKind {
+        match *self {
+            GameFile::Lst(_) => FileKind::Lst,
+            GameFile::Mid(_) => FileKind::Mid,
+            GameFile::Rle(_) => FileKind::Rle,
+            GameFile::Rmd(_) => FileKind::Rmd,
+            GameFile::Rmi(_) => FileKind::Rmi,
+            GameFile::Rmm(_) => FileKind::Rmm,
+            GameFile::Rms(_) => FileKind::Rms,
+        }
+    }
+}
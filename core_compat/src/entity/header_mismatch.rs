@@ -0,0 +1,17 @@
+use crate::entity::file_kind::FileKind;
+use crate::entity::list_version::ListVersion;
+use crate::entity::rmd_layout::RmdLayout;
+
+/// A file whose name or header advertises something else than what its
+/// content turned out to be; the content wins.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HeaderMismatch {
+    /// The file extension names a different format than the content
+    Extension { extension: FileKind, content: FileKind },
+    /// The list header advertises a version whose item layout doesn't fit
+    ListVersion { advertised: String, layout: ListVersion },
+    /// The body of an rmd doesn't fit the layout registered for its file type, `layout`, which
+    /// is registered for another one, does
+    RmdLayout { file_type: String, layout: RmdLayout },
+}
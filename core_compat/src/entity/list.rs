@@ -1,17 +1,42 @@
 
+use crate::entity::entry::Entry;
 use crate::entity::list_item::ListItem;
+use crate::entity::list_version::ListVersion;
+use crate::entity::text::Text;
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct List {
+    pub version: ListVersion,
+    pub next_free_id: u32,
     pub items: Vec<ListItem>,
 }
 
 impl List {
     pub fn new() -> List {
         List {
+            version: ListVersion::V1_0,
+            next_free_id: 0,
             items: Vec::new()
         }
     }
 
+    /// Hands out the next unused item ID. The stored `next_free_id` is
+    /// trusted unless an existing item already uses it or a larger ID.
+    pub fn allocate_id(&mut self) -> u32 {
+        let max_used = self.items.iter().map(|item| item.id + 1).max().unwrap_or(0);
+        let id = self.next_free_id.max(max_used);
+        self.next_free_id = id + 1;
+        id
+    }
+
+    /// Appends a new item pointing at `entry` and returns its allocated ID
+    pub fn add_item(&mut self, name: Text, entry: Entry) -> u32 {
+        let id = self.allocate_id();
+        self.items.push(ListItem { name, id, entry, unknown_2: 0 });
+        id
+    }
+
     pub fn get_item(&self, index: usize) -> Option<&ListItem> {
         for item in self.items.iter() {
             if item.id as usize == index {
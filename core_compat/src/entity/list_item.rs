@@ -1,8 +1,11 @@
 use crate::entity::entry::Entry;
+use crate::entity::text::Text;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ListItem {
-    pub name: String,
+    pub name: Text,
     pub id: u32,
     pub entry: Entry, // Entry { File number, File Index }
+    pub unknown_2: u32, // only stored in version 1.2 files
 }
@@ -0,0 +1,9 @@
+/// The layouts of the `RedMoon Lst File` list files
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ListVersion {
+    /// Used in most of the list files
+    V1_0,
+    /// Adds an extra `unknown_2` word to every item; seems to only be used in `obj.lst`
+    V1_2,
+}
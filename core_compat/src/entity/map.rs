@@ -1,13 +1,15 @@
 
+use crate::entity::direction::Direction;
 use crate::entity::event::Event;
 use crate::entity::map_tile::MapTile;
+use crate::entity::text::Text;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Map {
     size_x: u32,
     size_y: u32,
-    id_count: u8,
-    id_list: Vec<u8>,
+    name: Text,
     number: u32,
     event_count: u32,
     events: Vec<Event>,
@@ -19,8 +21,7 @@ impl Map {
         Map {
             size_x: 0,
             size_y: 0,
-            id_count: 0,
-            id_list: Vec::new(),
+            name: Text::default(),
             number: 0,
             event_count: 0,
             events: Vec::new(),
@@ -36,10 +37,6 @@ impl Map {
         self.events.push(event);
     }
 
-    pub fn add_id_list_val(&mut self, val: u8) {
-        self.id_list.push(val);
-    }
-
     pub fn set_event_count(&mut self, event_count: u32) {
         self.event_count = event_count;
     }
@@ -48,8 +45,9 @@ impl Map {
         self.number = number;
     }
 
-    pub fn set_id_count(&mut self, val: u8) {
-        self.id_count = val;
+    /// The string after the map size; it seems to be the map's name
+    pub fn set_name(&mut self, name: Text) {
+        self.name = name;
     }
 
     pub fn set_size_x(&mut self, val: u32) {
@@ -60,10 +58,6 @@ impl Map {
         self.size_y = val;
     }
 
-    pub fn id_count(&self) -> u8 {
-        self.id_count
-    }
-
     pub fn event_count(&self) -> u32 {
         self.event_count
     }
@@ -76,6 +70,16 @@ impl Map {
         self.size_y
     }
 
+    pub fn name(&self) -> &Text {
+        &self.name
+    }
+
+    /// All of the event rectangles in file order; entries with a `number`
+    /// of 0 are unused placeholders but are kept so the map can be rewritten.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
     pub fn tiles(&self) -> &[MapTile] {
         &self.tiles
     }
@@ -88,6 +92,81 @@ impl Map {
         self.tiles.get(index)
     }
 
+    /// The index into `tiles()` of the tile at (`x`, `y`)
+    pub fn tile_index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.size_x || y >= self.size_y {
+            return None;
+        }
+        let index = y as usize * self.size_x as usize + x as usize;
+        if index < self.tiles.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// The (`x`, `y`) coordinates of the tile at `index` in `tiles()`
+    pub fn tile_coords(&self, index: usize) -> Option<(u32, u32)> {
+        if index >= self.tiles.len() || self.size_x == 0 {
+            return None;
+        }
+        let stride = self.size_x as usize;
+        Some(((index % stride) as u32, (index / stride) as u32))
+    }
+
+    pub fn tile_at(&self, x: u32, y: u32) -> Option<&MapTile> {
+        self.tile_index(x, y).map(|index| &self.tiles[index])
+    }
+
+    /// All of the tiles in file order (row by row) together with their coordinates
+    pub fn enumerate_tiles<'a>(&'a self) -> impl Iterator<Item = ((u32, u32), &'a MapTile)> + 'a {
+        let stride = self.size_x.max(1);
+        self.tiles.iter()
+            .enumerate()
+            .map(move |(index, tile)| (((index as u32) % stride, (index as u32) / stride), tile))
+    }
+
+    /// The tiles of the `width` by `height` rectangle starting at (`x`, `y`), row by row;
+    /// the parts of the rectangle outside of the map are left out.
+    pub fn region<'a>(
+        &'a self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32
+    ) -> impl Iterator<Item = ((u32, u32), &'a MapTile)> + 'a {
+        let x_end = x.saturating_add(width).min(self.size_x);
+        let y_end = y.saturating_add(height).min(self.size_y);
+        (y..y_end).flat_map(move |ty| {
+            (x..x_end).filter_map(move |tx| self.tile_at(tx, ty).map(|tile| ((tx, ty), tile)))
+        })
+    }
+
+    /// The coordinates of the tile next to (`x`, `y`) in `direction` on the screen, if it is
+    /// on the map
+    pub fn neighbour(&self, x: u32, y: u32, direction: Direction) -> Option<(u32, u32)> {
+        let (dx, dy) = direction.offset();
+        let nx = x as i64 + dx as i64;
+        let ny = y as i64 + dy as i64;
+        if nx < 0 || ny < 0 {
+            return None;
+        }
+        self.tile_index(nx as u32, ny as u32).map(|_| (nx as u32, ny as u32))
+    }
+
+    /// The tiles around (`x`, `y`), clockwise starting at `Direction::Up`
+    pub fn neighbours<'a>(
+        &'a self,
+        x: u32,
+        y: u32
+    ) -> impl Iterator<Item = (Direction, (u32, u32), &'a MapTile)> + 'a {
+        Direction::ALL.iter().filter_map(move |&direction| {
+            self.neighbour(x, y, direction).and_then(|(nx, ny)| {
+                self.tile_at(nx, ny).map(|tile| (direction, (nx, ny), tile))
+            })
+        })
+    }
+
     pub fn get_size_x(&self) -> u32 {
         self.size_x
     }
@@ -0,0 +1,15 @@
+/// How the tiles of a map file are stored, picked by the identifier in its header.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MapLayout {
+    /// Bytes taken by every tile, at least 8; the ones after the eight known bytes are skipped.
+    /// The parser rejects a shorter length.
+    pub tile_len: u64,
+}
+
+impl Default for MapLayout {
+    /// The `RedMoon MapData 1.0` layout of the 3.9 client
+    fn default() -> MapLayout {
+        MapLayout { tile_len: 8 }
+    }
+}
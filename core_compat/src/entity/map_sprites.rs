@@ -0,0 +1,44 @@
+use crate::entity::tile_sprite::TileSprite;
+use crate::error::Error;
+
+/// The sprites of every tile of a map, resolved once by `GameData::resolve_map` so the
+/// references don't have to be followed again for every frame that is drawn
+#[derive(Debug)]
+pub struct MapSprites {
+    size_x: u32,
+    tiles: Vec<Vec<TileSprite>>,
+    errors: Vec<((u32, u32), Error)>,
+}
+
+impl MapSprites {
+    pub fn new(size_x: u32) -> MapSprites {
+        MapSprites {
+            size_x,
+            tiles: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Adds the next tile in the order of the map file, (`x`, `y`) are only kept with the
+    /// errors of the parts that didn't resolve
+    pub fn add_tile(&mut self, x: u32, y: u32, sprites: Vec<TileSprite>, errors: Vec<Error>) {
+        self.tiles.push(sprites);
+        self.errors.extend(errors.into_iter().map(|err| ((x, y), err)));
+    }
+
+    /// The sprites of the tile at (`x`, `y`), none for coordinates outside of the map
+    pub fn tile_at(&self, x: u32, y: u32) -> &[TileSprite] {
+        if x >= self.size_x {
+            return &[];
+        }
+        match self.tiles.get(y as usize * self.size_x as usize + x as usize) {
+            Some(sprites) => sprites,
+            None => &[],
+        }
+    }
+
+    /// Why parts of the tiles are missing, by the coordinates of the tile
+    pub fn errors(&self) -> &[((u32, u32), Error)] {
+        &self.errors
+    }
+}
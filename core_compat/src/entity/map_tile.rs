@@ -1,11 +1,51 @@
+use crate::entity::collision::{Collision, SubSpot};
 use crate::entity::entry::Entry;
 
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MapTile {
     pub obj_rmd_entry: Entry,
     pub tle_rmd_entry: Entry,
-    pub warp: u32,
-    pub collision: u32,
+    pub warp: u32, // raw mouse indicator byte, see `is_warp`
+    pub collision: u32, // raw collision byte, see `collision_kind`
+    pub unknown_1: u8, // low two bits of the first tile byte
+    pub unknown_2: u8, // sixth tile byte
+}
+
+impl MapTile {
+    /// The mouse indicator value of the tiles that belong to a warp
+    pub const WARP_INDICATOR: u32 = 16;
+
+    /// A tile without an object, floor tile, warp or collision
+    pub fn new() -> MapTile {
+        MapTile {
+            obj_rmd_entry: Entry::new(0, 0),
+            tle_rmd_entry: Entry::new(0, 0),
+            warp: 0,
+            collision: 0,
+            unknown_1: 0,
+            unknown_2: 0,
+        }
+    }
+
+    pub fn collision_kind(&self) -> Collision {
+        Collision::from_raw(self.collision as u8)
+    }
+
+    pub fn is_walkable(&self, spot: SubSpot) -> bool {
+        self.collision_kind().is_walkable(spot)
+    }
+
+    /// Whether the mouse turns into the warp indicator over this tile
+    pub fn is_warp(&self) -> bool {
+        self.warp == MapTile::WARP_INDICATOR
+    }
+}
+
+impl Default for MapTile {
+    fn default() -> MapTile {
+        MapTile::new()
+    }
 }
 
 // NOTE: The `Entry` struct Looks something like :
@@ -0,0 +1,83 @@
+use crate::entity::midi_track::MidiTrack;
+
+/// How the delta times of the events are to be read
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MidiTiming {
+    /// Ticks per quarter note; the tempo map gives the length of a quarter note
+    TicksPerQuarter(u16),
+    /// SMPTE frames per second and ticks per frame
+    Smpte(u8, u8),
+}
+
+/// A "Set Tempo" meta event at an absolute tick
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TempoChange {
+    pub tick: u64,
+    pub micros_per_quarter: u32,
+}
+
+/// The tempo used until the first "Set Tempo" event (120 bpm)
+pub const DEFAULT_MICROS_PER_QUARTER: u32 = 500_000;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Midi {
+    pub format: u16,
+    pub timing: MidiTiming,
+    pub tracks: Vec<MidiTrack>,
+    /// Tempo changes of all tracks, ordered by tick
+    pub tempo_map: Vec<TempoChange>,
+}
+
+impl Midi {
+    pub fn new() -> Midi {
+        Midi {
+            format: 0,
+            timing: MidiTiming::TicksPerQuarter(96),
+            tracks: Vec::new(),
+            tempo_map: Vec::new(),
+        }
+    }
+
+    /// Converts an absolute tick into microseconds from the start of the song
+    pub fn tick_to_micros(&self, tick: u64) -> u64 {
+        match self.timing {
+            MidiTiming::Smpte(fps, ticks_per_frame) => {
+                let ticks_per_second = fps as u64 * ticks_per_frame as u64;
+                (tick * 1_000_000).checked_div(ticks_per_second).unwrap_or(0)
+            }
+            MidiTiming::TicksPerQuarter(tpq) => {
+                if tpq == 0 {
+                    return 0;
+                }
+                let tpq = tpq as u64;
+                let mut micros = 0u64;
+                let mut last_tick = 0u64;
+                let mut tempo = DEFAULT_MICROS_PER_QUARTER as u64;
+                for change in self.tempo_map.iter().take_while(|c| c.tick <= tick) {
+                    micros += (change.tick - last_tick) * tempo / tpq;
+                    last_tick = change.tick;
+                    tempo = change.micros_per_quarter as u64;
+                }
+                micros + (tick - last_tick) * tempo / tpq
+            }
+        }
+    }
+
+    /// The length of the song in ticks
+    pub fn tick_count(&self) -> u64 {
+        self.tracks.iter()
+            .filter_map(|track| track.events.last())
+            .map(|event| event.tick)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for Midi {
+    fn default() -> Midi {
+        Midi::new()
+    }
+}
@@ -0,0 +1,53 @@
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MidiEventKind {
+    NoteOff { channel: u8, key: u8, velocity: u8 },
+    NoteOn { channel: u8, key: u8, velocity: u8 },
+    PolyPressure { channel: u8, key: u8, pressure: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+    ChannelPressure { channel: u8, pressure: u8 },
+    /// 14 bit value, 0x2000 is the centre
+    PitchBend { channel: u8, value: u16 },
+    /// Data of a 0xF0 or 0xF7 system exclusive message
+    SysEx(Vec<u8>),
+    Meta { kind: u8, data: Vec<u8> },
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MidiEvent {
+    /// Ticks since the previous event of the track
+    pub delta: u32,
+    /// Ticks since the start of the track
+    pub tick: u64,
+    pub kind: MidiEventKind,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MidiTrack {
+    pub events: Vec<MidiEvent>,
+}
+
+impl MidiTrack {
+    pub fn new() -> MidiTrack {
+        MidiTrack { events: Vec::new() }
+    }
+
+    /// The text of the first "Track Name" meta event
+    pub fn name(&self) -> Option<String> {
+        self.events.iter().filter_map(|event| match event.kind {
+            MidiEventKind::Meta { kind: 0x03, ref data } => {
+                Some(String::from_utf8_lossy(data).into_owned())
+            }
+            _ => None,
+        }).next()
+    }
+}
+
+impl Default for MidiTrack {
+    fn default() -> MidiTrack {
+        MidiTrack::new()
+    }
+}
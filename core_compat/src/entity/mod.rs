@@ -1,16 +1,41 @@
+pub mod collision;
+pub mod color_key;
+pub mod direction;
+pub mod draw_type;
 pub mod entry;
 pub mod event;
+pub mod fallback;
+pub mod file_kind;
+pub mod game_file;
+pub mod header_mismatch;
 pub mod list;
 pub mod list_item;
+pub mod list_version;
 pub mod map;
+pub mod map_layout;
+pub mod map_sprites;
 pub mod map_tile;
+pub mod midi;
+pub mod midi_track;
+pub mod pixel_format;
+pub mod render_layer;
 pub mod resource;
 pub mod resource_file;
 pub mod rmd;
 pub mod rmd_animation;
+pub mod rmd_builder;
 pub mod rmd_image;
+pub mod rmd_layout;
 pub mod rmd_entry;
 pub mod rmd_type;
+pub mod sound;
 pub mod sprite;
 pub mod sprite_type;
+pub mod text;
+pub mod tile_sprite;
 pub mod rmi;
+pub mod rmi_action;
+pub mod rmi_event;
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests;
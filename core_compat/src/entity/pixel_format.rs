@@ -0,0 +1,19 @@
+/// The layout of the decoded `image_raw` buffer of a `Resource` / `Sprite`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PixelFormat {
+    /// 4 bytes per pixel, r, g, b, a
+    Rgba8888,
+    /// 2 bytes per pixel, the little endian r5g6b5 value as stored in the RLE file;
+    /// transparency is kept in a separate coverage mask
+    R5g6b5,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            PixelFormat::Rgba8888 => 4,
+            PixelFormat::R5g6b5 => 2,
+        }
+    }
+}
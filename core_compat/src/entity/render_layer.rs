@@ -0,0 +1,41 @@
+/// The layer an `RmdImage` is drawn on, decoded from its `render_z` value. The images of an
+/// entry are drawn from the back to the front, which puts e.g. a weapon behind or in front
+/// of the body of a character.
+///
+/// Experimental until `test_rmd_game_draw_types` in the rmd parser has been run on the game's
+/// Chr, Obj and Bul files, which prints the counts. The map loader used to flag object images with a `render_z` other than 0 or 2, which is all
+/// that is known about them; that a lower value is drawn further back is a guess, and so
+/// is `Middle`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RenderLayer {
+    /// 0: below everything else, floor decorations and shadows
+    Back,
+    /// 1: not seen so far
+    Middle,
+    /// 2: the usual layer of objects and character parts
+    Front,
+    /// Any other value, ordered by the value itself
+    Other(i32),
+}
+
+impl RenderLayer {
+    pub fn from_raw(raw: i32) -> RenderLayer {
+        match raw {
+            0 => RenderLayer::Back,
+            1 => RenderLayer::Middle,
+            2 => RenderLayer::Front,
+            raw => RenderLayer::Other(raw),
+        }
+    }
+
+    /// The `render_z` value, which is also the drawing order of the layers
+    pub fn raw(&self) -> i32 {
+        match *self {
+            RenderLayer::Back => 0,
+            RenderLayer::Middle => 1,
+            RenderLayer::Front => 2,
+            RenderLayer::Other(raw) => raw,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::utility::pixel::Pixel;
+use crate::entity::pixel_format::PixelFormat;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Resource {
     pub file_num: Option<u32>,
     index: u32,
@@ -15,7 +17,10 @@ pub struct Resource {
     pub unknown_3: u32,
     pub unknown_4: u32,
     // pub image: Vec<Pixel>,
+    pub pixel_format: PixelFormat,
     pub image_raw: Vec<u8>,
+    /// Coverage of the `R5g6b5` images, one byte per pixel (0x00 or 0xFF)
+    pub mask: Vec<u8>,
 }
 
 impl Resource {
@@ -33,7 +38,9 @@ impl Resource {
             unknown_2: 0,
             unknown_3: 0,
             unknown_4: 0,
+            pixel_format: PixelFormat::Rgba8888,
             image_raw: Vec::new(),
+            mask: Vec::new(),
         }
     }
 
@@ -1,5 +1,7 @@
 use crate::entity::resource::Resource;
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ResourceFile {
     pub name: String,
     pub file_number: u32,
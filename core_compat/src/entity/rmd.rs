@@ -1,11 +1,18 @@
 use crate::entity::rmd_type::RmdType;
 use crate::entity::rmd_animation::RmdAnimation;
 use crate::entity::rmd_entry::RmdEntry;
+use crate::entity::text::Text;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rmd {
     kind: RmdType,
-    string: String,
+    // header
+    file_type: Text,
+    file_number: u32,
+    padding: [u32; 2],
+    string_1: Text,
+    string_2: Text,
     animation_parts: i32,
     // for object animations
     animation_entry_count: i32,
@@ -19,7 +26,11 @@ impl Rmd {
     pub fn new(kind: RmdType) -> Rmd {
         Rmd {
             kind,
-            string: String::new(),
+            file_type: Text::default(),
+            file_number: 0,
+            padding: [0; 2],
+            string_1: Text::default(),
+            string_2: Text::default(),
             animation_parts: 0,
             animation_entry_count: 0,
             entry_count: 0,
@@ -29,6 +40,51 @@ impl Rmd {
         }
     }
 
+    pub fn kind(&self) -> RmdType {
+        self.kind
+    }
+
+    pub fn set_file_type(&mut self, file_type: Text) {
+        self.file_type = file_type;
+    }
+
+    pub fn file_type(&self) -> &Text {
+        &self.file_type
+    }
+
+    pub fn set_file_number(&mut self, number: u32) {
+        self.file_number = number;
+    }
+
+    pub fn file_number(&self) -> u32 {
+        self.file_number
+    }
+
+    pub fn set_padding(&mut self, padding: [u32; 2]) {
+        self.padding = padding;
+    }
+
+    pub fn padding(&self) -> [u32; 2] {
+        self.padding
+    }
+
+    /// The header strings are padded with NUL bytes, which the raw bytes keep
+    pub fn set_string_1(&mut self, string: Text) {
+        self.string_1 = string;
+    }
+
+    pub fn string_1(&self) -> &Text {
+        &self.string_1
+    }
+
+    pub fn set_string_2(&mut self, string: Text) {
+        self.string_2 = string;
+    }
+
+    pub fn string_2(&self) -> &Text {
+        &self.string_2
+    }
+
     pub fn add_animation(&mut self, ani: RmdAnimation) {
         self.animations.push(ani);
     }
@@ -55,6 +111,10 @@ impl Rmd {
         self.animation_entry_count = value;
     }
 
+    pub fn animation_entry_count(&self) -> i32 {
+        self.animation_entry_count
+    }
+
     pub fn add_entry(&mut self, entry: RmdEntry) {
         self.entries.push(entry);
     }
@@ -63,6 +123,10 @@ impl Rmd {
         self.entries.get(index)
     }
 
+    pub fn entries(&self) -> &[RmdEntry] {
+        &self.entries
+    }
+
     pub fn set_entry_count(&mut self, value: i32) {
         self.entry_count = value;
     }
@@ -1,5 +1,6 @@
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RmdAnimation {
     frame_count: i32,
     frames: Vec<i16> // Rmd row pointer
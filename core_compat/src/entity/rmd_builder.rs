@@ -0,0 +1,95 @@
+use crate::entity::rmd::Rmd;
+use crate::entity::rmd_animation::RmdAnimation;
+use crate::entity::rmd_entry::RmdEntry;
+use crate::entity::rmd_image::RmdImage;
+use crate::entity::rmd_type::RmdType;
+use crate::entity::text::Text;
+
+/// Assembles an `Rmd` from scratch while keeping all of the stored counts
+/// in line with the entries, images and animations that were added.
+///
+/// ```
+/// use core_compat::entity::rmd_builder::RmdBuilder;
+/// use core_compat::entity::rmd_image::RmdImage;
+/// use core_compat::entity::rmd_type::RmdType;
+///
+/// let rmd = RmdBuilder::new(RmdType::Object)
+///     .file_number(12)
+///     .entry(vec![
+///         RmdImage::new()
+///             .with_source(0, 0, 48, 24)
+///             .with_dest(-24, -12)
+///             .with_render_z(2)
+///             .with_image_ids(&[100]),
+///     ])
+///     .animation(&[0])
+///     .build();
+/// assert_eq!(rmd.entry_count(), 1);
+/// ```
+pub struct RmdBuilder {
+    rmd: Rmd,
+}
+
+impl RmdBuilder {
+    pub fn new(kind: RmdType) -> RmdBuilder {
+        RmdBuilder { rmd: Rmd::new(kind) }
+    }
+
+    pub fn file_type(mut self, file_type: &str) -> RmdBuilder {
+        self.rmd.set_file_type(Text::from(file_type));
+        self
+    }
+
+    pub fn file_number(mut self, number: u32) -> RmdBuilder {
+        self.rmd.set_file_number(number);
+        self
+    }
+
+    /// Sets the raw (cp949 encoded) header strings
+    pub fn strings(mut self, string_1: &[u8], string_2: &[u8]) -> RmdBuilder {
+        self.rmd.set_string_1(Text::from_raw(string_1.to_vec()));
+        self.rmd.set_string_2(Text::from_raw(string_2.to_vec()));
+        self
+    }
+
+    pub fn animation_parts(mut self, parts: i32) -> RmdBuilder {
+        self.rmd.set_animation_parts(parts);
+        self
+    }
+
+    pub fn animation_entry_count(mut self, count: i32) -> RmdBuilder {
+        self.rmd.set_animation_entry_count(count);
+        self
+    }
+
+    /// Appends an entry made up of `images`; the entry's index is the number
+    /// of entries added before it.
+    pub fn entry(mut self, images: Vec<RmdImage>) -> RmdBuilder {
+        let mut entry = RmdEntry::new();
+        entry.set_image_count(images.len() as i32);
+        for mut img in images {
+            img.image_id_count = img.image_id.len() as i32;
+            entry.add_image(img);
+        }
+        self.rmd.add_entry(entry);
+        self
+    }
+
+    /// Appends an animation whose frames point at entries of this file
+    pub fn animation(mut self, frames: &[i16]) -> RmdBuilder {
+        let mut ani = RmdAnimation::new(frames.len() as i32);
+        for frame in frames {
+            ani.add_frame(*frame);
+        }
+        self.rmd.add_animation(ani);
+        self
+    }
+
+    pub fn build(mut self) -> Rmd {
+        let entry_count = self.rmd.entries().len() as i32;
+        let animation_count = self.rmd.animations().len() as i32;
+        self.rmd.set_entry_count(entry_count);
+        self.rmd.set_animation_count(animation_count);
+        self.rmd
+    }
+}
@@ -1,7 +1,8 @@
 use crate::entity::rmd_animation::RmdAnimation;
 use crate::entity::rmd_image::RmdImage;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RmdEntry {
     image_count: i32,
     images: Vec<RmdImage>,
@@ -34,5 +35,12 @@ impl RmdEntry {
     pub fn images(&self) -> &[RmdImage] {
         &self.images
     }
+
+    /// The images in drawing order: by layer, keeping the file order within a layer
+    pub fn images_by_layer(&self) -> Vec<&RmdImage> {
+        let mut images: Vec<&RmdImage> = self.images.iter().collect();
+        images.sort_by_key(|img| img.layer().raw());
+        images
+    }
 }
 
@@ -2,7 +2,11 @@ use geometry::rectangle::Rectangle;
 use geometry::size::Size;
 use geometry::point::Point;
 
-#[derive(Debug)]
+use crate::entity::draw_type::DrawType;
+use crate::entity::render_layer::RenderLayer;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RmdImage {
     pub source_x1: i32,
     pub source_y1: i32,
@@ -10,10 +14,10 @@ pub struct RmdImage {
     pub source_y2: i32,
     pub empty_1: i32,
     pub empty_2: i32,
-    pub render_z: i32,
+    pub render_z: i32, // see `layer`
     pub dest_x: i32,
     pub dest_y: i32,
-    pub draw_type: i32, // enum { Shadow, skill, normal }
+    pub draw_type: i32, // see `draw_kind`
     pub image_id_count: i32,
     pub image_id: Vec<i32>    // Lst row/entry pointer entries
 }
@@ -30,10 +34,52 @@ impl RmdImage {
             render_z: 0,
             dest_x: 0,
             dest_y: 0,
-            draw_type: 0,
+            draw_type: DrawType::Normal.raw(),
             image_id_count: 0,
             image_id: Vec::new(),
         }
     }
+
+    pub fn with_source(mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> RmdImage {
+        self.source_x1 = x1;
+        self.source_y1 = y1;
+        self.source_x2 = x2;
+        self.source_y2 = y2;
+        self
+    }
+
+    pub fn with_dest(mut self, x: i32, y: i32) -> RmdImage {
+        self.dest_x = x;
+        self.dest_y = y;
+        self
+    }
+
+    pub fn with_render_z(mut self, render_z: i32) -> RmdImage {
+        self.render_z = render_z;
+        self
+    }
+
+    pub fn with_draw_type(mut self, draw_type: i32) -> RmdImage {
+        self.draw_type = draw_type;
+        self
+    }
+
+    pub fn with_image_ids(mut self, ids: &[i32]) -> RmdImage {
+        self.image_id = ids.to_vec();
+        self.image_id_count = ids.len() as i32;
+        self
+    }
+
+    pub fn draw_kind(&self) -> DrawType {
+        DrawType::from_raw(self.draw_type)
+    }
+
+    pub fn is_shadow(&self) -> bool {
+        self.draw_kind() == DrawType::Shadow
+    }
+
+    pub fn layer(&self) -> RenderLayer {
+        RenderLayer::from_raw(self.render_z)
+    }
 }
 
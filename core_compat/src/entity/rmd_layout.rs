@@ -0,0 +1,14 @@
+/// How the body of an rmd file is stored, picked by the file type string in its header.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RmdLayout {
+    /// Bytes taken by every animation frame, either 2 or 4; the parser rejects any other length
+    pub frame_len: u8,
+}
+
+impl Default for RmdLayout {
+    /// The layout of the 3.9 client
+    fn default() -> RmdLayout {
+        RmdLayout { frame_len: 2 }
+    }
+}
@@ -1,5 +1,6 @@
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RmdType {
     Bullet,
     Character,
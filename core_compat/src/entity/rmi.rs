@@ -1,10 +1,38 @@
+use crate::entity::rmi_event::RmiEvent;
+use crate::entity::text::Text;
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rmi {
+    file_type: Text,
+    events: Vec<RmiEvent>,
 }
 
 impl Rmi {
     pub fn new() -> Rmi {
         Rmi {
+            file_type: Text::default(),
+            events: Vec::new(),
         }
     }
+
+    pub fn set_file_type(&mut self, file_type: Text) {
+        self.file_type = file_type;
+    }
+
+    pub fn file_type(&self) -> &Text {
+        &self.file_type
+    }
+
+    pub fn add_event(&mut self, event: RmiEvent) {
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[RmiEvent] {
+        &self.events
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
 }
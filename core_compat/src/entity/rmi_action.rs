@@ -0,0 +1,9 @@
+use crate::entity::text::Text;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RmiAction {
+    pub action_timeout: i32,
+    pub trigger: Text,
+    pub action: Text,
+}
@@ -0,0 +1,12 @@
+use crate::entity::rmi_action::RmiAction;
+
+/// The only event block type seen in the version 3.9 event files
+pub const RMI_EVENT_TYPE_68: i32 = 68;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RmiEvent {
+    pub event_type: i32,
+    pub pad: [u8; 2],
+    pub actions: Vec<RmiAction>,
+}
@@ -0,0 +1,106 @@
+//! The entities survive a JSON round trip, checked by writing them back into their file layout.
+
+use serde_json;
+
+use crate::entity::entry::Entry;
+use crate::entity::event::Event;
+use crate::entity::list::List;
+use crate::entity::list_version::ListVersion;
+use crate::entity::map::Map;
+use crate::entity::map_tile::MapTile;
+use crate::entity::resource::Resource;
+use crate::entity::resource_file::ResourceFile;
+use crate::entity::rmd::Rmd;
+use crate::entity::rmd_builder::RmdBuilder;
+use crate::entity::rmd_image::RmdImage;
+use crate::entity::rmd_type::RmdType;
+use crate::entity::text::Text;
+use crate::writer::lst::write_lst;
+use crate::writer::rle::write_rle;
+use crate::writer::rmd::write_rmd;
+use crate::writer::rmm::write_rmm;
+
+#[test]
+fn test_map_serde_round_trip() {
+    let mut map = Map::new();
+    map.set_size_x(2);
+    map.set_size_y(1);
+    map.set_name(Text::encode("마을").unwrap());
+    map.set_map_number(7);
+    map.add_event(Event { number: 5, left: 1, top: 2, right: 3, bottom: 4 });
+    map.add_tile(MapTile {
+        obj_rmd_entry: Entry::new(2047, 510),
+        tle_rmd_entry: Entry::new(511, 1023),
+        warp: 16,
+        collision: 48,
+        unknown_1: 0x1,
+        unknown_2: 0xAA,
+    });
+    map.add_tile(MapTile::new());
+
+    let json = serde_json::to_string(&map).unwrap();
+    let restored: Map = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.name(), map.name());
+    assert_eq!(restored.tiles(), map.tiles());
+    assert_eq!(write_rmm(&restored).unwrap(), write_rmm(&map).unwrap());
+}
+
+#[test]
+fn test_rmd_serde_round_trip() {
+    let rmd = RmdBuilder::new(RmdType::Character)
+        .file_type("character")
+        .entry(vec![
+            RmdImage::new().with_source(0, 0, 20, 30).with_image_ids(&[1, 2]),
+            RmdImage::new().with_render_z(0).with_draw_type(0).with_image_ids(&[3]),
+        ])
+        .animation(&[0, -1])
+        .build();
+
+    let json = serde_json::to_string(&rmd).unwrap();
+    let restored: Rmd = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, rmd);
+    assert_eq!(write_rmd(&restored).unwrap(), write_rmd(&rmd).unwrap());
+}
+
+#[test]
+fn test_list_serde_round_trip() {
+    let mut list = List::new();
+    list.version = ListVersion::V1_2;
+    list.add_item(Text::encode("나무").unwrap(), Entry::new(1, 0));
+    // bytes that aren't valid cp949 are kept next to their replacement text
+    list.add_item(Text::from_raw(vec![0xFF, 0x41]), Entry::new(7, 42));
+    list.items[1].unknown_2 = 0xCAFE;
+
+    let json = serde_json::to_string(&list).unwrap();
+    let restored: List = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.version, list.version);
+    assert_eq!(restored.next_free_id, list.next_free_id);
+    assert_eq!(restored.items, list.items);
+    assert_eq!(
+        write_lst(&restored, restored.version).unwrap(),
+        write_lst(&list, list.version).unwrap()
+    );
+}
+
+#[test]
+fn test_resource_file_serde_round_trip() {
+    let mut resource = Resource::new();
+    resource.set_index(1);
+    resource.offset_x = -3;
+    resource.offset_y = 7;
+    resource.width = 2;
+    resource.height = 1;
+    resource.unknown_4 = 0xDEAD_BEEF;
+    resource.image_raw = vec![0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00];
+    let mut resource_file = ResourceFile::new();
+    resource_file.name = "tle00001.rle".into();
+    resource_file.file_number = 1;
+    resource_file.resources.push(resource);
+
+    let json = serde_json::to_string(&resource_file).unwrap();
+    let restored: ResourceFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.name, resource_file.name);
+    assert_eq!(restored.resources[0].index(), 1);
+    assert_eq!(restored.resources[0].image_raw, resource_file.resources[0].image_raw);
+    assert_eq!(write_rle(&restored).unwrap(), write_rle(&resource_file).unwrap());
+}
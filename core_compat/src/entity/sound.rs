@@ -0,0 +1,52 @@
+/// The sample encodings found in the sound files
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SampleFormat {
+    /// 8 bit unsigned PCM
+    U8,
+    /// 16 bit signed little endian PCM
+    I16,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sound {
+    pub format: SampleFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Bytes in front of the wave data, if the file has its own header
+    pub header: Vec<u8>,
+    /// Interleaved PCM samples in `format`
+    pub data: Vec<u8>,
+}
+
+impl Sound {
+    pub fn new() -> Sound {
+        Sound {
+            format: SampleFormat::I16,
+            sample_rate: 0,
+            channels: 0,
+            header: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn bytes_per_sample(&self) -> usize {
+        match self.format {
+            SampleFormat::U8 => 1,
+            SampleFormat::I16 => 2,
+        }
+    }
+
+    /// The number of sample frames, i.e. samples per channel
+    pub fn frame_count(&self) -> usize {
+        let frame_len = self.bytes_per_sample() * self.channels as usize;
+        self.data.len().checked_div(frame_len).unwrap_or(0)
+    }
+}
+
+impl Default for Sound {
+    fn default() -> Sound {
+        Sound::new()
+    }
+}
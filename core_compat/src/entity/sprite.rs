@@ -1,8 +1,10 @@
 use crate::utility::pixel::Pixel;
 use crate::entity::entry::Entry;
+use crate::entity::pixel_format::PixelFormat;
 use crate::entity::sprite_type::SpriteType;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sprite {
     pub class: SpriteType,
     pub rle_entry: Entry,
@@ -11,6 +13,8 @@ pub struct Sprite {
     pub x_off: i32,
     pub y_off: i32,
     // pub image_raw: Vec<Pixel>,
-    pub image_raw: Vec<u8>, // defined to be in RGB_565_norm format
+    pub pixel_format: PixelFormat,
+    pub image_raw: Vec<u8>, // laid out as `pixel_format`
+    pub mask: Vec<u8>, // coverage of `R5g6b5` images, one byte per pixel
 }
 
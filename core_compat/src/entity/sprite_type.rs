@@ -1,5 +1,7 @@
+use crate::entity::color_key::ColorKey;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SpriteType {
     Bullet,
     Icon,
@@ -8,3 +10,14 @@ pub enum SpriteType {
     Tile,
     Interface,
 }
+
+impl SpriteType {
+    /// The colour key the sprites of this type are read with. Only the object and interface
+    /// sprites are drawn over magenta; in the others a magenta pixel is a real colour.
+    pub fn color_key(&self) -> ColorKey {
+        match *self {
+            SpriteType::Object | SpriteType::Interface => ColorKey::Magenta,
+            _ => ColorKey::Off,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+use cp949::{cp949_to_utf8, cp949_to_utf8_strict, utf8_to_cp949, utf8_to_cp949_lossy, EncodeError};
+
+/// A string of a game file. The files store their text as cp949, those bytes are kept as is
+/// so the file can be written back unchanged, next to the text they decode to.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Text {
+    raw: Vec<u8>,
+    text: String,
+}
+
+impl Text {
+    /// Decodes the bytes of a file; some strings are padded with NUL bytes, which aren't part
+    /// of the text, and invalid sequences become U+FFFD.
+    pub fn from_raw(raw: Vec<u8>) -> Text {
+        let text = cp949_to_utf8(&without_nul(&raw));
+        Text { raw, text }
+    }
+
+    /// Encodes the text for a file, failing on characters that cp949 doesn't have
+    pub fn encode(text: &str) -> Result<Text, EncodeError> {
+        Ok(Text { raw: utf8_to_cp949(text)?, text: text.into() })
+    }
+
+    /// The bytes as they are stored in the file
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the stored bytes are valid cp949, the text of invalid ones holds U+FFFD
+    pub fn is_valid(&self) -> bool {
+        cp949_to_utf8_strict(&without_nul(&self.raw)).is_ok()
+    }
+}
+
+/// Encodes the text lossily, the characters that cp949 doesn't have become a `?`
+impl<'a> From<&'a str> for Text {
+    fn from(text: &'a str) -> Text {
+        Text::from_raw(utf8_to_cp949_lossy(text))
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq<str> for Text {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Text {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.text == *other
+    }
+}
+
+fn without_nul(raw: &[u8]) -> Vec<u8> {
+    raw.iter().cloned().filter(|&b| b != 0).collect()
+}
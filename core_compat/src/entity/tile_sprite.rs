@@ -0,0 +1,22 @@
+use crate::entity::draw_type::DrawType;
+use crate::entity::entry::Entry;
+use crate::entity::render_layer::RenderLayer;
+use crate::entity::sprite_type::SpriteType;
+
+/// One sprite that a map tile draws, with the RMM -> RMD -> LST -> RLE chain resolved
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TileSprite {
+    pub sprite_type: SpriteType,
+    pub rmd_entry: Entry, // the RMD file and entry the image came from
+    pub list_id: u32,
+    pub rle_entry: Entry, // the RLE file and resource index
+    pub source_x1: i32,
+    pub source_y1: i32,
+    pub source_x2: i32,
+    pub source_y2: i32,
+    pub dest_x: i32,
+    pub dest_y: i32,
+    pub layer: RenderLayer,
+    pub draw_type: DrawType,
+}
@@ -1,19 +1,102 @@
+use std::error;
+use std::fmt;
 use std::io;
 use std::str::Utf8Error;
 use std::string::FromUtf16Error;
 use std::string::FromUtf8Error;
 
+use crate::entity::file_kind::FileKind;
+use crate::entity::rmd_type::RmdType;
+
 #[derive(Debug)]
 pub enum Error {
+    DuplicateResourceIndex(u32),
     FromUtf16(FromUtf16Error),
     FromUtf8(FromUtf8Error),
+    InvalidImageSize(u32),
+    /// The map's `size_x` by `size_y` doesn't match the number of its tiles
+    InvalidMapSize { size_x: u32, size_y: u32, tiles: usize },
+    InvalidMapTile(usize),
     Io(io::Error),
-    MissingMapIdentifier,
-    MissingRleIdentifier,
-    UnknownOffsetTypeAt(u64),
+    /// The file doesn't start with the identifier of its format
+    MissingIdentifier { kind: FileKind, offset: u64, expected: String, found: String },
+    /// An entry that another file refers to isn't there; `file` is 0 for the list files
+    MissingReference { kind: FileKind, file: u32, index: u32 },
+    StringTooLong(usize),
+    /// The file ends in the middle of a structure
+    UnexpectedEof { kind: FileKind, offset: u64 },
+    /// A value that the format doesn't allow, or that isn't understood yet
+    UnexpectedValue { kind: FileKind, offset: u64, expected: String, found: String },
+    /// None of the formats could be recognized
+    UnknownFormat { found: String },
+    /// The files of this data type can't be located yet
+    UnsupportedData(RmdType),
     Utf8(Utf8Error),
 }
 
+impl Error {
+    /// Attaches the file kind and the position of the cursor to the errors raised
+    /// by the readers, errors that already carry a position are left as is.
+    pub(crate) fn in_file(self, kind: FileKind, offset: u64) -> Error {
+        match self {
+            Error::Io(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                Error::UnexpectedEof { kind, offset }
+            }
+            Error::Utf8(_) | Error::FromUtf8(_) | Error::FromUtf16(_) => {
+                Error::UnexpectedValue {
+                    kind,
+                    offset,
+                    expected: "a valid string".into(),
+                    found: self.to_string(),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::DuplicateResourceIndex(idx) => write!(f, "duplicate resource index {}", idx),
+            Error::FromUtf16(ref err) => write!(f, "{}", err),
+            Error::FromUtf8(ref err) => write!(f, "{}", err),
+            Error::InvalidImageSize(idx) => write!(f, "resource {} has an invalid image size", idx),
+            Error::InvalidMapSize { size_x, size_y, tiles } => write!(
+                f, "a map of {}x{} tiles holds {} tiles", size_x, size_y, tiles),
+            Error::InvalidMapTile(idx) => write!(f, "map tile {} can't be encoded", idx),
+            Error::Io(ref err) => write!(f, "{}", err),
+            Error::MissingIdentifier { kind, offset, ref expected, ref found } => write!(
+                f, "{} file: expected identifier {:?} at offset {:#x}, found {:?}",
+                kind, expected, offset, found),
+            Error::MissingReference { kind, file, index } => write!(
+                f, "{} file {}: entry {} doesn't exist", kind, file, index),
+            Error::StringTooLong(len) => write!(f, "string of {} bytes is too long", len),
+            Error::UnexpectedEof { kind, offset } => write!(
+                f, "{} file: unexpected end of file at offset {:#x}", kind, offset),
+            Error::UnexpectedValue { kind, offset, ref expected, ref found } => write!(
+                f, "{} file: expected {} at offset {:#x}, found {}",
+                kind, expected, offset, found),
+            Error::UnknownFormat { ref found } => write!(
+                f, "unknown file format, starting with {:?}", found),
+            Error::UnsupportedData(kind) => write!(f, "{:?} data can't be located", kind),
+            Error::Utf8(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::FromUtf16(ref err) => Some(err),
+            Error::FromUtf8(ref err) => Some(err),
+            Error::Io(ref err) => Some(err),
+            Error::Utf8(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::Io(err)
@@ -5,9 +5,16 @@ extern crate geometry;
 extern crate cp949;
 // external
 extern crate byteorder;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 pub mod error;
 pub mod utility;
 pub mod parser;
+pub mod writer;
 pub mod entity;
+pub mod repository;
 
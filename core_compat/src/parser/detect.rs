@@ -0,0 +1,483 @@
+//! Recognizes the game files by their content, so they can be parsed without
+//! knowing their format upfront.
+//!
+//! Most of the formats start with an identifier:
+//! - RLE: "Resource File\0"
+//! - RMM: length prefixed "RedMoon MapData 1.0"
+//! - LST: length prefixed "RedMoon Lst File"
+//! - RMI: length prefixed "RedMoon EventInfo File 1.0"
+//! - MID: "MThd", or a RIFF "RMID" wrapper
+//! - RMS: a RIFF "WAVE" image, possibly after a short header
+//!
+//! The RMD files have no identifier; a file is taken as one when its header has
+//! the RMD shape: the file type, file number, padding, two more strings and the
+//! counts, with text in the strings and counts that the rest of the file can
+//! hold. Their `RmdType` comes from the file name ("tle00001.rmd") or else from
+//! the folder ("Tle").
+//!
+//! Some files advertise the wrong header, so the content decides the format
+//! and every disagreement is reported as a `HeaderMismatch`.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use byteorder::ReadBytesExt;
+use byteorder::LittleEndian as LE;
+
+use crate::error::Error;
+use crate::entity::fallback::Fallback;
+use crate::entity::file_kind::FileKind;
+use crate::entity::game_file::GameFile;
+use crate::entity::header_mismatch::HeaderMismatch;
+use crate::entity::list::List;
+use crate::entity::list_version::ListVersion;
+use crate::entity::rmd::Rmd;
+use crate::entity::rmd_layout::RmdLayout;
+use crate::entity::rmd_type::RmdType;
+use crate::entity::text::Text;
+use crate::parser::lst::{parse_lst_with, parse_lst_lenient_with};
+use crate::parser::mid::parse_mid;
+use crate::parser::registry::VersionRegistry;
+use crate::parser::rle::parse_rle;
+use crate::parser::rmd::{parse_rmd_with, parse_rmd_len};
+use crate::parser::rmi::parse_rmi;
+use crate::parser::rmm::parse_rmm_with;
+use crate::parser::rms::{parse_rms, find_riff};
+use crate::utility::parsing::parse_text;
+
+const RLE_IDENTIFIER: &[u8; 14] = b"Resource File\0";
+const RMM_IDENTIFIER: &[u8] = b"RedMoon MapData 1.0";
+const LST_IDENTIFIER: &[u8] = b"RedMoon Lst File";
+const RMI_IDENTIFIER: &[u8] = b"RedMoon EventInfo File 1.0";
+
+/// What `parse_any` reads from; the name of a `Named` source is only used for
+/// its extension, the `RmdType` and the RLE file number.
+pub enum Source<'a> {
+    Path(&'a Path),
+    Bytes(&'a [u8]),
+    Named(&'a Path, &'a [u8]),
+}
+
+impl<'a> From<&'a Path> for Source<'a> {
+    fn from(path: &'a Path) -> Source<'a> {
+        Source::Path(path)
+    }
+}
+
+impl<'a> From<&'a PathBuf> for Source<'a> {
+    fn from(path: &'a PathBuf) -> Source<'a> {
+        Source::Path(path)
+    }
+}
+
+impl<'a> From<&'a str> for Source<'a> {
+    fn from(path: &'a str) -> Source<'a> {
+        Source::Path(Path::new(path))
+    }
+}
+
+impl<'a> From<&'a [u8]> for Source<'a> {
+    fn from(data: &'a [u8]) -> Source<'a> {
+        Source::Bytes(data)
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for Source<'a> {
+    fn from(data: &'a Vec<u8>) -> Source<'a> {
+        Source::Bytes(data)
+    }
+}
+
+impl<'a> From<(&'a str, &'a [u8])> for Source<'a> {
+    fn from((name, data): (&'a str, &'a [u8])) -> Source<'a> {
+        Source::Named(Path::new(name), data)
+    }
+}
+
+/// The format of `data`, judged by its content only
+pub fn detect(data: &[u8]) -> Option<FileKind> {
+    if data.starts_with(RLE_IDENTIFIER) {
+        return Some(FileKind::Rle);
+    }
+    match prefixed_string(data) {
+        Some(RMM_IDENTIFIER) => return Some(FileKind::Rmm),
+        Some(LST_IDENTIFIER) => return Some(FileKind::Lst),
+        Some(RMI_IDENTIFIER) => return Some(FileKind::Rmi),
+        _ => (),
+    }
+    if data.starts_with(b"MThd")
+        || (data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"RMID") {
+        return Some(FileKind::Mid);
+    }
+    if find_riff(data).is_some() {
+        return Some(FileKind::Rms);
+    }
+    if rmd_file_type(data).is_some() {
+        return Some(FileKind::Rmd);
+    }
+    None
+}
+
+/// Detects the format of the source and parses it, together with every place
+/// where the file's name or header disagrees with its content.
+pub fn parse_any<'a, S>(source: S) -> Result<(GameFile, Vec<HeaderMismatch>), Error>
+    where S: Into<Source<'a>>
+{
+    parse_any_with(source, &VersionRegistry::new())
+}
+
+/// Same as `parse_any`, reading the files with the layouts of `versions`
+pub fn parse_any_with<'a, S>(
+    source: S,
+    versions: &VersionRegistry
+) -> Result<(GameFile, Vec<HeaderMismatch>), Error>
+    where S: Into<Source<'a>>
+{
+    let read;
+    let (name, data) = match source.into() {
+        Source::Path(path) => {
+            read = fs::read(path)?;
+            (Some(path), &read[..])
+        }
+        Source::Bytes(data) => (None, data),
+        Source::Named(path, data) => (Some(path), data),
+    };
+
+    let mut mismatches = Vec::<HeaderMismatch>::new();
+    let extension = name
+        .and_then(|path| path.extension())
+        .and_then(|ext| ext.to_str())
+        .and_then(FileKind::from_extension);
+    // fall back to the extension for files without an identifier that don't parse cleanly
+    let kind = match detect(data).or(extension) {
+        Some(kind) => kind,
+        None => return Err(Error::UnknownFormat {
+            found: String::from_utf8_lossy(&data[..data.len().min(16)]).into(),
+        }),
+    };
+    if let Some(extension) = extension {
+        if extension != kind {
+            mismatches.push(HeaderMismatch::Extension { extension, content: kind });
+        }
+    }
+
+    let file = match kind {
+        FileKind::Lst => GameFile::Lst(parse_list(data, versions, &mut mismatches)?),
+        FileKind::Mid => GameFile::Mid(parse_mid(data)?),
+        FileKind::Rle => GameFile::Rle(parse_rle(name.map(file_number).unwrap_or(0), data)?),
+        FileKind::Rmd => {
+            let rmd_type = match name.and_then(rmd_type) {
+                Some(rmd_type) => rmd_type,
+                None => return Err(Error::UnexpectedValue {
+                    kind: FileKind::Rmd,
+                    offset: 0,
+                    expected: "a file or folder name starting with bul, chr, ico, obj or tle".into(),
+                    found: format!("{:?}", name),
+                }),
+            };
+            GameFile::Rmd(parse_rmd_layouts(rmd_type, data, versions, &mut mismatches)?)
+        }
+        FileKind::Rmi => GameFile::Rmi(parse_rmi(data)?),
+        FileKind::Rmm => GameFile::Rmm(parse_rmm_with(data, versions)?),
+        FileKind::Rms => GameFile::Rms(parse_rms(data)?),
+    };
+    Ok((file, mismatches))
+}
+
+/// Parses a list with the layout its header advertises, unless only the other
+/// layout covers the file; `snd.lst` for example advertises 1.0 but is laid out as 1.2.
+fn parse_list(
+    data: &[u8],
+    versions: &VersionRegistry,
+    mismatches: &mut Vec<HeaderMismatch>
+) -> Result<List, Error> {
+    let (list, fallbacks) = parse_lst_lenient_with(data, versions)?;
+    for fallback in fallbacks {
+        match fallback {
+            Fallback::ListLayout { advertised, layout } => {
+                mismatches.push(HeaderMismatch::ListVersion { advertised, layout });
+            }
+            // a damaged list is only accepted by the lenient parser itself
+            _ => return parse_lst_with(data, versions),
+        }
+    }
+    Ok(list)
+}
+
+/// A string prefixed with its length in a single byte
+fn prefixed_string(data: &[u8]) -> Option<&[u8]> {
+    let len = *data.first()? as usize;
+    data.get(1..1 + len)
+}
+
+/// Parses an rmd with the layout `versions` has for its file type. When the body
+/// doesn't fit that layout, i.e. it fails to parse or doesn't end with the file, but
+/// it does fit a layout registered for another file type, the file type is reported as
+/// a mismatch. Otherwise the result of its own layout is returned, so a damaged file
+/// keeps its error instead of being read with a layout no release is known to use.
+fn parse_rmd_layouts(
+    rmd_type: RmdType,
+    data: &[u8],
+    versions: &VersionRegistry,
+    mismatches: &mut Vec<HeaderMismatch>
+) -> Result<Rmd, Error> {
+    let fits = |versions: &VersionRegistry| match parse_rmd_len(rmd_type, data, versions) {
+        Ok((rmd, len)) if len == data.len() as u64 => Some(rmd),
+        _ => None,
+    };
+    if let Some(rmd) = fits(versions) {
+        return Ok(rmd);
+    }
+    if let Some(file_type) = rmd_file_type(data) {
+        let registered = versions.rmd_layout(file_type.as_str());
+        let layouts = versions.rmd_layouts();
+        for &layout in layouts.iter().filter(|&&layout| layout != registered) {
+            let mut other = versions.clone();
+            other.register_rmd(file_type.as_str(), layout);
+            if let Some(rmd) = fits(&other) {
+                mismatches.push(HeaderMismatch::RmdLayout {
+                    file_type: file_type.to_string(),
+                    layout,
+                });
+                return Ok(rmd);
+            }
+        }
+    }
+    parse_rmd_with(rmd_type, data, versions)
+}
+
+/// The file type of an RMD header, if `data` starts with one; see the module docs
+fn rmd_file_type(data: &[u8]) -> Option<Text> {
+    let mut cursor = Cursor::new(data);
+    let file_type = read_header_text(&mut cursor)?;
+    // the file number and the 8 padding bytes
+    cursor.set_position(cursor.position() + 12);
+    read_header_text(&mut cursor)?;
+    let animation_parts = cursor.read_i32::<LE>().ok()?;
+    let animation_entries = cursor.read_i32::<LE>().ok()?;
+    read_header_text(&mut cursor)?;
+    let entry_count = cursor.read_i32::<LE>().ok()?;
+    if animation_parts < 0 || animation_entries < 0 || entry_count < 0 {
+        return None;
+    }
+    // every entry starts with its image count and the animation count follows them
+    let remaining = (data.len() as u64).saturating_sub(cursor.position());
+    if (entry_count as u64 + 1) * 4 > remaining {
+        return None;
+    }
+    Some(file_type)
+}
+
+/// A header string, which holds cp949 text and NUL padding but no other control characters
+fn read_header_text(cursor: &mut Cursor<&[u8]>) -> Option<Text> {
+    let text = parse_text(cursor).ok()?;
+    if text.raw().iter().all(|&b| b == 0 || b >= 0x20) {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+fn rmd_type(path: &Path) -> Option<RmdType> {
+    let stem = path.file_stem().and_then(|stem| stem.to_str());
+    let folder = path.parent()
+        .and_then(|parent| parent.file_name())
+        .and_then(|folder| folder.to_str());
+    stem.and_then(rmd_type_from_prefix)
+        .or_else(|| folder.and_then(rmd_type_from_prefix))
+}
+
+fn rmd_type_from_prefix(name: &str) -> Option<RmdType> {
+    let name = name.to_lowercase();
+    if name.starts_with("bul") {
+        Some(RmdType::Bullet)
+    } else if name.starts_with("chr") {
+        Some(RmdType::Character)
+    } else if name.starts_with("ico") {
+        Some(RmdType::Icon)
+    } else if name.starts_with("obj") {
+        Some(RmdType::Object)
+    } else if name.starts_with("tle") {
+        Some(RmdType::Tile)
+    } else {
+        None
+    }
+}
+
+/// The number in a file name like "obj00012.rle", 0 if there is none
+fn file_number(path: &Path) -> u32 {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem.matches(char::is_numeric).collect::<String>())
+        .and_then(|digits| digits.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::entry::Entry;
+    use crate::entity::map::Map;
+    use crate::entity::resource_file::ResourceFile;
+    use crate::entity::rmd_builder::RmdBuilder;
+    use crate::entity::rmd_image::RmdImage;
+    use crate::writer::lst::write_lst;
+    use crate::writer::rle::write_rle;
+    use crate::writer::rmd::write_rmd;
+    use crate::writer::rmm::write_rmm;
+
+    fn make_rmd() -> Vec<u8> {
+        let rmd = RmdBuilder::new(RmdType::Tile)
+            .file_number(3)
+            .entry(vec![RmdImage::new().with_source(0, 0, 64, 32).with_image_ids(&[5])])
+            .animation(&[0])
+            .build();
+        write_rmd(&rmd).unwrap()
+    }
+
+    // the offset of the entry count in `make_rmd`, after three empty strings
+    const RMD_ENTRY_COUNT: usize = 1 + 12 + 1 + 8 + 1;
+
+    fn make_list(version: ListVersion) -> Vec<u8> {
+        let mut list = List::new();
+        list.add_item("a".into(), Entry::new(1, 2));
+        list.add_item("b".into(), Entry::new(3, 4));
+        write_lst(&list, version).unwrap()
+    }
+
+    fn make_rmi() -> Vec<u8> {
+        let mut data = vec![RMI_IDENTIFIER.len() as u8];
+        data.extend_from_slice(RMI_IDENTIFIER);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data
+    }
+
+    fn make_wave() -> Vec<u8> {
+        let mut data = b"RIFF\x24\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        data.extend_from_slice(&[0x40, 0x1F, 0, 0, 0x40, 0x1F, 0, 0, 1, 0, 8, 0]);
+        data.extend_from_slice(b"data\0\0\0\0");
+        data
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&write_rle(&ResourceFile::new()).unwrap()), Some(FileKind::Rle));
+        assert_eq!(detect(&write_rmm(&Map::new()).unwrap()), Some(FileKind::Rmm));
+        assert_eq!(detect(&make_list(ListVersion::V1_0)), Some(FileKind::Lst));
+        assert_eq!(detect(&make_rmi()), Some(FileKind::Rmi));
+        assert_eq!(detect(b"MThd\0\0\0\x06\0\0\0\0\0\x60"), Some(FileKind::Mid));
+        assert_eq!(detect(&make_wave()), Some(FileKind::Rms));
+        assert_eq!(detect(&make_rmd()), Some(FileKind::Rmd));
+
+        // only the header is looked at, the body is left to the parser
+        let mut rmd = make_rmd();
+        rmd.push(0);
+        assert_eq!(detect(&rmd), Some(FileKind::Rmd));
+        // a control character in the file type
+        let mut rmd = make_rmd();
+        rmd[0] = 1;
+        rmd.insert(1, 0x07);
+        assert_eq!(detect(&rmd), None);
+        // more entries than the rest of the file can hold
+        let mut rmd = make_rmd();
+        rmd[RMD_ENTRY_COUNT..RMD_ENTRY_COUNT + 4].copy_from_slice(&1000i32.to_le_bytes());
+        assert_eq!(detect(&rmd), None);
+        assert_eq!(detect(b""), None);
+    }
+
+    #[test]
+    fn test_parse_any_names() {
+        let rmd = make_rmd();
+        match parse_any(("DATAs/Tle/tle00003.rmd", &rmd[..])).unwrap() {
+            (GameFile::Rmd(ref rmd), ref mismatches) if mismatches.is_empty() => {
+                assert_eq!(rmd.kind(), RmdType::Tile);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_any(("Chr/00001.rmd", &rmd[..])).unwrap() {
+            (GameFile::Rmd(ref rmd), _) => assert_eq!(rmd.kind(), RmdType::Character),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_any(&rmd) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rmd, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let rle = write_rle(&ResourceFile::new()).unwrap();
+        match parse_any(("obj00012.rle", &rle[..])).unwrap() {
+            (GameFile::Rle(ref file), _) => assert_eq!(file.file_number, 12),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_any(&b"nothing to see here"[..]) {
+            Err(Error::UnknownFormat { .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_any_reports_mismatches() {
+        let rmi = make_rmi();
+        let (file, mismatches) = parse_any(("event00.rmm", &rmi[..])).unwrap();
+        assert_eq!(file.kind(), FileKind::Rmi);
+        assert_eq!(mismatches, vec![
+            HeaderMismatch::Extension { extension: FileKind::Rmm, content: FileKind::Rmi },
+        ]);
+
+        // a 1.2 layout behind a 1.0 header, like snd.lst
+        let mut lst = make_list(ListVersion::V1_2);
+        let version = LST_IDENTIFIER.len() + 4;
+        lst[version] = b'0';
+        let (file, mismatches) = parse_any(("snd.lst", &lst[..])).unwrap();
+        match file {
+            GameFile::Lst(ref list) => assert_eq!(list.items.len(), 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(mismatches, vec![
+            HeaderMismatch::ListVersion { advertised: "1.0".into(), layout: ListVersion::V1_2 },
+        ]);
+
+        let lst = make_list(ListVersion::V1_0);
+        let (_, mismatches) = parse_any(("obj.lst", &lst[..])).unwrap();
+        assert!(mismatches.is_empty());
+
+        // an rmd with four byte frames behind a file type without a registered layout, the
+        // layout is only tried when another file type uses it
+        let rmd = RmdBuilder::new(RmdType::Object)
+            .file_type("wide")
+            .entry(vec![RmdImage::new().with_image_ids(&[1])])
+            .entry(vec![RmdImage::new().with_image_ids(&[2])])
+            .animation(&[0, 1])
+            .build();
+        let data = write_rmd(&rmd).unwrap();
+        let frames_offset = data.len() - 4;
+        let mut wide = data[..frames_offset].to_vec();
+        wide.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0]);
+        let (file, mismatches) = parse_any(("obj00001.rmd", &wide[..])).unwrap();
+        assert!(mismatches.is_empty());
+        match file {
+            // the two byte frames of its own layout, with the rest left over
+            GameFile::Rmd(ref parsed) => assert_ne!(*parsed, rmd),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut versions = VersionRegistry::new();
+        versions.register_rmd("newer", RmdLayout { frame_len: 4 });
+        let (file, mismatches) = parse_any_with(("obj00001.rmd", &wide[..]), &versions).unwrap();
+        match file {
+            GameFile::Rmd(ref parsed) => assert_eq!(*parsed, rmd),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(mismatches, vec![
+            HeaderMismatch::RmdLayout { file_type: "wide".into(), layout: RmdLayout { frame_len: 4 } },
+        ]);
+        let mut versions = VersionRegistry::new();
+        versions.register_rmd("wide", RmdLayout { frame_len: 4 });
+        let (_, mismatches) = parse_any_with(("obj00001.rmd", &wide[..]), &versions).unwrap();
+        assert!(mismatches.is_empty());
+
+        // a body that fits no layout keeps the error
+        wide.truncate(frames_offset + 1);
+        assert!(parse_any(("obj00001.rmd", &wide[..])).is_err());
+        assert!(parse_any_with(("obj00001.rmd", &wide[..]), &versions).is_err());
+    }
+}
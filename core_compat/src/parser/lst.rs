@@ -1,4 +1,3 @@
-use std::str::from_utf8;
 use std::io::Cursor;
 use std::io::Seek;
 use std::io::SeekFrom;
@@ -7,109 +6,208 @@ use byteorder::ReadBytesExt;
 use byteorder::LittleEndian as LE;
 
 use crate::error::Error;
+use crate::entity::file_kind::FileKind;
 use crate::entity::entry::Entry;
 use crate::entity::list::List;
 use crate::entity::list_item::ListItem;
+use crate::entity::list_version::ListVersion;
+use crate::entity::fallback::Fallback;
+use crate::parser::registry::VersionRegistry;
+use crate::entity::text::Text;
+use crate::utility::parsing::parse_text;
+
+const LST_IDENTIFIER: &str = "RedMoon Lst File";
 
 pub fn parse_lst(data: &[u8], use_v2: bool) -> Result<List, Error> {
     let mut cursor = Cursor::new(data);
+    read_lst(&mut cursor, use_v2, &VersionRegistry::new())
+        .map_err(|err| err.in_file(FileKind::Lst, cursor.position()))
+}
+
+/// Reads the list with the layout that `versions` has for the version in its header
+pub fn parse_lst_with(data: &[u8], versions: &VersionRegistry) -> Result<List, Error> {
+    let mut cursor = Cursor::new(data);
+    read_lst(&mut cursor, false, versions)
+        .map_err(|err| err.in_file(FileKind::Lst, cursor.position()))
+}
+
+fn read_lst(
+    cursor: &mut Cursor<&[u8]>,
+    use_v2: bool,
+    versions: &VersionRegistry
+) -> Result<List, Error> {
     // filetype len prefixed string:
     //  - needs to equal "RedMoon Lst File"
-    let string_length = cursor.read_u8()?;
-    let mut string = Vec::<u8>::new();
-    for _ in 0..string_length {
-        let chr = cursor.read_u8()?;
-        string.push(chr);
-    }
-    {
-        let file_type: &str = from_utf8(&string)?;
-        // println!("{:?}", &file_type);
-    }
+    let file_type = parse_text(cursor)?;
     // file version length prefixed string
-    let version: &str;
-    {
-        let version_length = cursor.read_u8()?;
-        string.clear();
-        for _ in 0..version_length {
-            let chr = cursor.read_u8()?;
-            string.push(chr);
-        }
-        version = from_utf8(&string)?;
-        // println!("{:?}", &version);
-    }
+    let version_offset = cursor.position();
+    let version = parse_text(cursor)?;
 
     if use_v2 {
-        load_1_2(&mut cursor)
+        load_1_2(cursor)
     } else {
-        match version {
-            "1.0" => load_1_0(&mut cursor),
-            "1.2" => load_1_2(&mut cursor),
-            _ => panic!("Unknown version type: {:?}", version),
+        match versions.list_layout(version.as_str()) {
+            Some(ListVersion::V1_0) => load_1_0(cursor),
+            Some(ListVersion::V1_2) => load_1_2(cursor),
+            None => Err(Error::UnexpectedValue {
+                kind: FileKind::Lst,
+                offset: version_offset,
+                expected: format!("version {}", versions.list_versions().iter()
+                    .map(|version| format!("{:?}", version))
+                    .collect::<Vec<String>>()
+                    .join(" or ")),
+                found: format!("{:?}", version.as_str()),
+            }),
         }
     }
 }
 
-/// The 1.0 format is used in most of the list files
-fn load_1_0(cursor: &mut Cursor<&[u8]>) -> Result<List, Error> {
-    let mut list = List::new();
-    let mut string = Vec::<u8>::new();
+/// Reads the list the way its header advertises; when the items don't add up to the file it
+/// tries the other layout, or else keeps the items that fit. Every deviation from the header
+/// is reported as a `Fallback`.
+pub fn parse_lst_lenient(data: &[u8]) -> Result<(List, Vec<Fallback>), Error> {
+    parse_lst_lenient_with(data, &VersionRegistry::new())
+}
 
-    // Unknown u32
-    let next_free_id = cursor.read_u32::<LE>()?;
-    // list Entry counts
-    let entry_count = cursor.read_u32::<LE>()?;
-    // read entries
-    for _ in 0..entry_count {
-        // entry name
-        let name_length = cursor.read_u8()?;
-        string.clear();
-        for _ in 0..name_length {
-            let chr = cursor.read_u8()?;
-            string.push(chr);
+/// `parse_lst_lenient` that knows the versions in `versions`
+pub fn parse_lst_lenient_with(
+    data: &[u8],
+    versions: &VersionRegistry
+) -> Result<(List, Vec<Fallback>), Error> {
+    let mut fallbacks = Vec::<Fallback>::new();
+    let mut cursor = Cursor::new(data);
+    let (file_type, version) = read_header(&mut cursor)
+        .map_err(|err| err.in_file(FileKind::Lst, cursor.position()))?;
+    if file_type.raw() != LST_IDENTIFIER.as_bytes() {
+        fallbacks.push(Fallback::IgnoredIdentifier { found: file_type.to_string() });
+    }
+    let advertised = version.to_string();
+    let layouts = match versions.list_layout(&advertised) {
+        Some(ListVersion::V1_2) => [ListVersion::V1_2, ListVersion::V1_0],
+        _ => [ListVersion::V1_0, ListVersion::V1_2],
+    };
+    let layout_fallback = |layout: ListVersion| {
+        if versions.list_layout(&advertised) == Some(layout) {
+            None
+        } else {
+            Some(Fallback::ListLayout { advertised: advertised.clone(), layout })
+        }
+    };
+
+    // a layout whose items end right at the end of the file
+    for &layout in layouts.iter() {
+        if let Ok((list, len)) = parse_lst_as(data, layout) {
+            if len == data.len() {
+                fallbacks.extend(layout_fallback(layout));
+                return Ok((list, fallbacks));
+            }
         }
-        let name = from_utf8(&string).unwrap_or("wrong encoding?!").into();
-        let id = cursor.read_u32::<LE>()?;
-        let file_number = cursor.read_u32::<LE>()?;
-        let index = cursor.read_u32::<LE>()?;
-        let entry = Entry::new(file_number, index);
-        // rest of entry info
-        let item = ListItem { name, id, entry};
-        list.items.push(item);
     }
-    Ok(list)
+
+    // a layout that reads every declared item with some data left over, or
+    // else as many items as fit with the advertised layout
+    let mut partial = None;
+    for &layout in layouts.iter() {
+        let mut cursor = Cursor::new(data);
+        let (list, declared) = read_header(&mut cursor)
+            .and_then(|_| load_items(&mut cursor, layout, true))
+            .map_err(|err| err.in_file(FileKind::Lst, cursor.position()))?;
+        if list.items.len() as u32 == declared {
+            fallbacks.extend(layout_fallback(layout));
+            fallbacks.push(Fallback::TrailingBytes {
+                offset: cursor.position(),
+                len: data.len() as u64 - cursor.position(),
+            });
+            return Ok((list, fallbacks));
+        }
+        if partial.is_none() {
+            partial = Some((list, declared));
+        }
+    }
+    let (list, declared) = partial.unwrap();
+    fallbacks.extend(layout_fallback(list.version));
+    fallbacks.push(Fallback::TruncatedEntries { declared, read: list.items.len() as u32 });
+    Ok((list, fallbacks))
+}
+
+/// Reads the items with the `layout`, whatever version the header advertises. Also returns how
+/// many bytes were read, which tells if the layout covers the whole file.
+pub(crate) fn parse_lst_as(data: &[u8], layout: ListVersion) -> Result<(List, usize), Error> {
+    let mut cursor = Cursor::new(data);
+    let list = read_lst_as(&mut cursor, layout)
+        .map_err(|err| err.in_file(FileKind::Lst, cursor.position()))?;
+    Ok((list, cursor.position() as usize))
+}
+
+fn read_lst_as(cursor: &mut Cursor<&[u8]>, layout: ListVersion) -> Result<List, Error> {
+    read_header(cursor)?;
+    match layout {
+        ListVersion::V1_0 => load_1_0(cursor),
+        ListVersion::V1_2 => load_1_2(cursor),
+    }
+}
+
+/// The file type and version strings
+fn read_header(cursor: &mut Cursor<&[u8]>) -> Result<(Text, Text), Error> {
+    let file_type = parse_text(cursor)?;
+    let version = parse_text(cursor)?;
+    Ok((file_type, version))
+}
+
+/// The 1.0 format is used in most of the list files
+fn load_1_0(cursor: &mut Cursor<&[u8]>) -> Result<List, Error> {
+    load_items(cursor, ListVersion::V1_0, false).map(|(list, _)| list)
 }
 
 /// The 1.2 format seems to only be used in the `Obj` rle list file
 fn load_1_2(cursor: &mut Cursor<&[u8]>) -> Result<List, Error> {
+    load_items(cursor, ListVersion::V1_2, false).map(|(list, _)| list)
+}
+
+/// Reads the items after the header and returns them with the declared item count. With
+/// `partial` the items are read until the first one that doesn't fit, instead of failing.
+fn load_items(
+    cursor: &mut Cursor<&[u8]>,
+    layout: ListVersion,
+    partial: bool
+) -> Result<(List, u32), Error> {
     let mut list = List::new();
-    let mut string = Vec::<u8>::new();
+    list.version = layout;
 
     // Unknown u32 -- assumed to be the next free ID
-    let next_free_id = cursor.read_u32::<LE>()?;
+    list.next_free_id = cursor.read_u32::<LE>()?;
     // list Entry counts
     let entry_count = cursor.read_u32::<LE>()?;
     // read entries
     for _ in 0..entry_count {
-        // entry name
-        let name_length = cursor.read_u8()?;
-        string.clear();
-        for _ in 0..name_length {
-            let chr = cursor.read_u8()?;
-            string.push(chr);
+        let pos = cursor.position();
+        match load_item(cursor, layout) {
+            Ok(item) => list.items.push(item),
+            Err(_) if partial => {
+                cursor.set_position(pos);
+                break;
+            }
+            Err(err) => return Err(err),
         }
-        let name = from_utf8(&string).unwrap_or("wrong encoding?!").into();
-        let id = cursor.read_u32::<LE>()?;
-        let file_number = cursor.read_u32::<LE>()?;
-        let index = cursor.read_u32::<LE>()?;
-        let entry = Entry::new(file_number, index);
-        // I'm sort of assuming that we're trying to link to the "next id?"
-        // here in the newer format with `unknown_2`?
-        let unknown_2 = cursor.read_u32::<LE>()?;
-        // rest of entry info
-        let item = ListItem { name, id, entry };
-        list.items.push(item);
     }
-    Ok(list)
+    Ok((list, entry_count))
+}
+
+fn load_item(cursor: &mut Cursor<&[u8]>, layout: ListVersion) -> Result<ListItem, Error> {
+    // entry name, often korean
+    let name = parse_text(cursor)?;
+    let id = cursor.read_u32::<LE>()?;
+    let file_number = cursor.read_u32::<LE>()?;
+    let index = cursor.read_u32::<LE>()?;
+    let entry = Entry::new(file_number, index);
+    // I'm sort of assuming that we're trying to link to the "next id?"
+    // here in the newer format with `unknown_2`?
+    let unknown_2 = match layout {
+        ListVersion::V1_0 => 0,
+        ListVersion::V1_2 => cursor.read_u32::<LE>()?,
+    };
+    // rest of entry info
+    Ok(ListItem { name, id, entry, unknown_2 })
 }
 
 #[cfg(test)]
@@ -140,21 +238,14 @@ mod tests {
         let list = parse_lst(data, false).unwrap();
     }
 
-    // #[test]
-    // // NOTE: This is the only one to fail on the 1.0 version
-    // fn test_lst_snd() {
-    //     let data = include_bytes!("../../data/RLEs/snd.lst");
-    //     println!("data.len() = 0x{:X} bytes", data.len());
-    //     let list = List::load(data, false);
-    //     let list = match list {
-    //         Ok(_) => list,
-    //         Err(_) => {
-    //             // maybe it really is in version 1.2 format?
-    //             List::load(data, true)
-    //         }
-    //     };
-    //     list.unwrap();
-    // }
+    #[test]
+    // NOTE: This is the only one to fail on the 1.0 version; it advertises
+    //       1.0 but the items are laid out like version 1.2
+    fn test_lst_snd() {
+        let data = include_bytes!("../../../data/RLEs/snd.lst");
+        assert!(parse_lst(data, false).is_err());
+        let list = parse_lst(data, true).unwrap();
+    }
 
     #[test]
     // NOTE: This uses the version 1.2 of the lst file
@@ -162,4 +253,70 @@ mod tests {
         let data = include_bytes!("../../../data/RLEs/obj.lst");
         let list = parse_lst(data, false).unwrap();
     }
+
+    fn make_list_bytes(layout: ListVersion) -> Vec<u8> {
+        use crate::writer::lst::write_lst;
+        let mut list = List::new();
+        for &(name, file) in [("a", 1), ("bb", 2), ("ccc", 3)].iter() {
+            list.add_item(name.into(), Entry::new(file, 0));
+        }
+        write_lst(&list, layout).unwrap()
+    }
+
+    #[test]
+    fn test_lst_lenient() {
+        let data = make_list_bytes(ListVersion::V1_0);
+        let (list, fallbacks) = parse_lst_lenient(&data).unwrap();
+        assert_eq!(list.items.len(), 3);
+        assert!(fallbacks.is_empty());
+
+        // advertises 1.0 but is laid out as 1.2
+        let mut data = make_list_bytes(ListVersion::V1_2);
+        data[20] = b'0';
+        let (list, fallbacks) = parse_lst_lenient(&data).unwrap();
+        assert_eq!(list.version, ListVersion::V1_2);
+        assert_eq!(list.items[2].name, "ccc");
+        assert_eq!(fallbacks, vec![
+            Fallback::ListLayout { advertised: "1.0".into(), layout: ListVersion::V1_2 },
+        ]);
+
+        let mut data = make_list_bytes(ListVersion::V1_0);
+        data.extend_from_slice(&[0xFF; 3]);
+        let (list, fallbacks) = parse_lst_lenient(&data).unwrap();
+        assert_eq!(list.items.len(), 3);
+        assert_eq!(fallbacks, vec![
+            Fallback::TrailingBytes { offset: data.len() as u64 - 3, len: 3 },
+        ]);
+
+        let mut data = make_list_bytes(ListVersion::V1_0);
+        let len = data.len() - 2;
+        data.truncate(len);
+        let (list, fallbacks) = parse_lst_lenient(&data).unwrap();
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(fallbacks, vec![Fallback::TruncatedEntries { declared: 3, read: 2 }]);
+
+        match parse_lst_lenient(&data[..20]) {
+            Err(Error::UnexpectedEof { kind: FileKind::Lst, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lst_text_names() {
+        use crate::writer::lst::write_lst;
+
+        let mut list = List::new();
+        list.add_item(Text::encode("나무").unwrap(), Entry::new(1, 0));
+        // not valid cp949, but it has to survive a rewrite
+        list.add_item(Text::from_raw(vec![b'x', 0x80, b'y']), Entry::new(1, 1));
+        let data = write_lst(&list, ListVersion::V1_0).unwrap();
+
+        let parsed = parse_lst(&data, false).unwrap();
+        assert_eq!(parsed.items[0].name, "나무");
+        assert_eq!(parsed.items[0].name.raw(), &[0xB3, 0xAA, 0xB9, 0xAB]);
+        assert!(parsed.items[0].name.is_valid());
+        assert_eq!(parsed.items[1].name, "x\u{FFFD}y");
+        assert!(!parsed.items[1].name.is_valid());
+        assert_eq!(write_lst(&parsed, ListVersion::V1_0).unwrap(), data);
+    }
 }
@@ -0,0 +1,346 @@
+//! Standard MIDI Files (format 0 and 1). All values are big endian.
+//!
+//! [HEADER] "MThd"
+//! u32 header length (6, longer headers are skipped over)
+//! u16 format
+//! u16 track count
+//! u16 division (ticks per quarter note, or SMPTE when the top bit is set)
+//!
+//! [TRACK] "MTrk"
+//! u32 track length
+//! events: variable length delta time followed by a channel message
+//!         (running status allowed), a 0xF0 / 0xF7 sysex or a 0xFF meta event
+//!
+//! None of the game's music files have been checked against this parser yet, so it doesn't
+//! know of any quirks of theirs. It is lenient in the ways that MIDI files found elsewhere
+//! commonly need:
+//! - The file may be wrapped in a RIFF "RMID" container
+//! - Chunks other than "MTrk" are skipped
+//! - A track whose length runs past the end of the file is cut off there
+//! - A track without an "End of Track" event ends with its chunk
+//! - Fewer tracks than the header announces
+//!
+//! As the standard says, meta and sysex events cancel the running status.
+
+use std::io::Cursor;
+
+use byteorder::ReadBytesExt;
+use byteorder::BigEndian as BE;
+
+use crate::error::Error;
+use crate::entity::file_kind::FileKind;
+use crate::entity::midi::{Midi, MidiTiming, TempoChange};
+use crate::entity::midi_track::{MidiTrack, MidiEvent, MidiEventKind};
+
+const META_END_OF_TRACK: u8 = 0x2F;
+const META_SET_TEMPO: u8 = 0x51;
+
+pub fn parse_mid(data: &[u8]) -> Result<Midi, Error> {
+    let data = unwrap_rmid(data);
+    let mut cursor = Cursor::new(data);
+    read_mid(&mut cursor).map_err(|err| err.in_file(FileKind::Mid, cursor.position()))
+}
+
+fn read_mid(cursor: &mut Cursor<&[u8]>) -> Result<Midi, Error> {
+    let data = *cursor.get_ref();
+    if data.len() < 14 || &data[0..4] != b"MThd" {
+        return Err(Error::MissingIdentifier {
+            kind: FileKind::Mid,
+            offset: 0,
+            expected: "MThd".into(),
+            found: String::from_utf8_lossy(&data[..data.len().min(4)]).into(),
+        });
+    }
+
+    cursor.set_position(4);
+    let header_len = cursor.read_u32::<BE>()? as u64;
+    let mut midi = Midi::new();
+    midi.format = cursor.read_u16::<BE>()?;
+    let track_count = cursor.read_u16::<BE>()?;
+    let division = cursor.read_u16::<BE>()?;
+    if midi.format > 1 {
+        return Err(Error::UnexpectedValue {
+            kind: FileKind::Mid,
+            offset: 8,
+            expected: "format 0 or 1".into(),
+            found: format!("format {}", midi.format),
+        });
+    }
+    midi.timing = if division & 0x8000 != 0 {
+        // the frame rate is stored as a negative number
+        let fps = ((division >> 8) as u8 as i8).wrapping_neg() as u8;
+        MidiTiming::Smpte(fps, (division & 0xFF) as u8)
+    } else {
+        MidiTiming::TicksPerQuarter(division)
+    };
+
+    let mut pos = 8 + header_len as usize;
+    while pos + 8 <= data.len() && midi.tracks.len() < track_count as usize {
+        let id = &data[pos..pos + 4];
+        cursor.set_position(pos as u64 + 4);
+        let len = cursor.read_u32::<BE>()? as usize;
+        let start = pos + 8;
+        let end = start.saturating_add(len).min(data.len());
+        if id == b"MTrk" {
+            midi.tracks.push(parse_track(&data[start..end], start as u64)?);
+        }
+        pos = end;
+    }
+
+    for track in midi.tracks.iter() {
+        for event in track.events.iter() {
+            if let MidiEventKind::Meta { kind: META_SET_TEMPO, ref data } = event.kind {
+                if data.len() == 3 {
+                    let micros_per_quarter = ((data[0] as u32) << 16)
+                        | ((data[1] as u32) << 8)
+                        | data[2] as u32;
+                    midi.tempo_map.push(TempoChange { tick: event.tick, micros_per_quarter });
+                }
+            }
+        }
+    }
+    midi.tempo_map.sort_by_key(|change| change.tick);
+
+    Ok(midi)
+}
+
+/// Returns the SMF image inside a RIFF "RMID" file, or the data itself
+fn unwrap_rmid(data: &[u8]) -> &[u8] {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"RMID" {
+        return data;
+    }
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let len = Cursor::new(&data[pos + 4..pos + 8]).read_u32::<byteorder::LittleEndian>()
+            .unwrap_or(0) as usize;
+        let start = pos + 8;
+        let end = start.saturating_add(len).min(data.len());
+        if &data[pos..pos + 4] == b"data" {
+            return &data[start..end];
+        }
+        pos = end + (len & 0x1);
+    }
+    data
+}
+
+/// `offset` is the position of the track data in the file, used for errors
+fn parse_track(data: &[u8], offset: u64) -> Result<MidiTrack, Error> {
+    let mut cursor = Cursor::new(data);
+    read_track(&mut cursor, offset)
+        .map_err(|err| err.in_file(FileKind::Mid, offset + cursor.position()))
+}
+
+fn read_track(cursor: &mut Cursor<&[u8]>, offset: u64) -> Result<MidiTrack, Error> {
+    let data = *cursor.get_ref();
+    let mut track = MidiTrack::new();
+    let mut running_status: Option<u8> = None;
+    let mut tick = 0u64;
+
+    while (cursor.position() as usize) < data.len() {
+        let delta = read_vlq(cursor)?;
+        tick += delta as u64;
+
+        let status_pos = offset + cursor.position();
+        let mut status = cursor.read_u8()?;
+        let mut first_data = None;
+        if status < 0x80 {
+            first_data = Some(status);
+            status = match running_status {
+                Some(status) => status,
+                None => return Err(unknown_status(status_pos, status)),
+            };
+        }
+
+        let kind = match status {
+            0x80..=0xEF => {
+                running_status = Some(status);
+                let channel = status & 0x0F;
+                let d1 = match first_data {
+                    Some(d) => d,
+                    None => cursor.read_u8()?,
+                };
+                match status & 0xF0 {
+                    0xC0 => MidiEventKind::ProgramChange { channel, program: d1 },
+                    0xD0 => MidiEventKind::ChannelPressure { channel, pressure: d1 },
+                    high => {
+                        let d2 = cursor.read_u8()?;
+                        match high {
+                            0x80 => MidiEventKind::NoteOff { channel, key: d1, velocity: d2 },
+                            0x90 => MidiEventKind::NoteOn { channel, key: d1, velocity: d2 },
+                            0xA0 => MidiEventKind::PolyPressure { channel, key: d1, pressure: d2 },
+                            0xB0 => MidiEventKind::ControlChange { channel, controller: d1, value: d2 },
+                            _ => MidiEventKind::PitchBend {
+                                channel,
+                                value: (d1 as u16 & 0x7F) | ((d2 as u16 & 0x7F) << 7),
+                            },
+                        }
+                    }
+                }
+            }
+            0xF0 | 0xF7 => {
+                running_status = None;
+                let len = read_vlq(cursor)?;
+                MidiEventKind::SysEx(read_bytes(cursor, len)?)
+            }
+            0xFF => {
+                running_status = None;
+                let kind = cursor.read_u8()?;
+                let len = read_vlq(cursor)?;
+                MidiEventKind::Meta { kind, data: read_bytes(cursor, len)? }
+            }
+            _ => return Err(unknown_status(status_pos, status)),
+        };
+
+        let is_end = matches!(kind, MidiEventKind::Meta { kind: META_END_OF_TRACK, .. });
+        track.events.push(MidiEvent { delta, tick, kind });
+        if is_end {
+            break;
+        }
+    }
+
+    Ok(track)
+}
+
+fn unknown_status(offset: u64, status: u8) -> Error {
+    Error::UnexpectedValue {
+        kind: FileKind::Mid,
+        offset,
+        expected: "a status byte".into(),
+        found: format!("{:#04x}", status),
+    }
+}
+
+/// Variable length quantity of at most 4 bytes, 7 bits per byte
+fn read_vlq(cursor: &mut Cursor<&[u8]>) -> Result<u32, Error> {
+    let mut value = 0u32;
+    for _ in 0..4 {
+        let byte = cursor.read_u8()?;
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(value)
+}
+
+fn read_bytes(cursor: &mut Cursor<&[u8]>, len: u32) -> Result<Vec<u8>, Error> {
+    let start = cursor.position() as usize;
+    let end = start + len as usize;
+    let data = *cursor.get_ref();
+    if end > data.len() {
+        return Err(Error::Io(::std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    cursor.set_position(end as u64);
+    Ok(data[start..end].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn make_midi() -> Vec<u8> {
+        let mut data = chunk(b"MThd", &[0, 1, 0, 2, 0, 96]);
+        // conductor track: 120 bpm, then 60 bpm after two quarter notes
+        data.extend(chunk(b"MTrk", &[
+            0x00, 0xFF, 0x03, 0x04, b'c', b'o', b'n', b'd',
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20,
+            0x81, 0x40, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40,
+            0x00, 0xFF, 0x2F, 0x00,
+        ]));
+        // an unknown chunk in between the tracks
+        data.extend(chunk(b"XFIH", &[1, 2, 3]));
+        // note track with running status and no End of Track event
+        data.extend(chunk(b"MTrk", &[
+            0x00, 0xC1, 0x05,
+            0x00, 0x91, 0x3C, 0x64,
+            0x60, 0x3C, 0x00,
+            0x00, 0xE1, 0x00, 0x40,
+            0x00, 0xF0, 0x02, 0x7E, 0xF7,
+        ]));
+        data
+    }
+
+    #[test]
+    fn test_mid_synthetic() {
+        let midi = parse_mid(&make_midi()).unwrap();
+        assert_eq!(midi.format, 1);
+        assert_eq!(midi.timing, MidiTiming::TicksPerQuarter(96));
+        assert_eq!(midi.tracks.len(), 2);
+        assert_eq!(midi.tracks[0].name(), Some("cond".into()));
+        assert_eq!(midi.tempo_map, vec![
+            TempoChange { tick: 0, micros_per_quarter: 500_000 },
+            TempoChange { tick: 192, micros_per_quarter: 1_000_000 },
+        ]);
+
+        let notes = &midi.tracks[1].events;
+        assert_eq!(notes.len(), 5);
+        assert_eq!(notes[0].kind, MidiEventKind::ProgramChange { channel: 1, program: 5 });
+        assert_eq!(notes[1].kind, MidiEventKind::NoteOn { channel: 1, key: 60, velocity: 100 });
+        assert_eq!(notes[2].kind, MidiEventKind::NoteOn { channel: 1, key: 60, velocity: 0 });
+        assert_eq!(notes[2].tick, 96);
+        assert_eq!(notes[3].kind, MidiEventKind::PitchBend { channel: 1, value: 0x2000 });
+        assert_eq!(notes[4].kind, MidiEventKind::SysEx(vec![0x7E, 0xF7]));
+
+        // two quarters at 120 bpm, then one at 60 bpm
+        assert_eq!(midi.tick_to_micros(192), 1_000_000);
+        assert_eq!(midi.tick_to_micros(288), 2_000_000);
+        assert_eq!(midi.tick_count(), 192);
+    }
+
+    #[test]
+    fn test_mid_rmid_wrapper_and_truncation() {
+        let smf = make_midi();
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(4 + 8 + smf.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"RMID");
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&(smf.len() as u32 + 100).to_le_bytes());
+        data.extend_from_slice(&smf);
+        let midi = parse_mid(&data).unwrap();
+        assert_eq!(midi.tracks.len(), 2);
+    }
+
+    #[test]
+    fn test_mid_smpte_and_errors() {
+        let mut data = chunk(b"MThd", &[0, 0, 0, 1, 0xE7, 40]);
+        data.extend(chunk(b"MTrk", &[0x00, 0x3C, 0x64]));
+        match parse_mid(&data) {
+            Err(Error::UnexpectedValue { kind: FileKind::Mid, offset: 23, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut data = chunk(b"MThd", &[0, 0, 0, 1, 0xE7, 40]);
+        data.extend(chunk(b"MTrk", &[0x00, 0xFF, 0x01, 0x05, b'a']));
+        match parse_mid(&data) {
+            Err(Error::UnexpectedEof { kind: FileKind::Mid, offset: 26 }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // a data byte after a meta event has no running status to use
+        let mut data = chunk(b"MThd", &[0, 0, 0, 1, 0xE7, 40]);
+        data.extend(chunk(b"MTrk", &[0x00, 0x91, 0x3C, 0x64, 0x00, 0xFF, 0x01, 0x00, 0x00, 0x3C, 0x00]));
+        match parse_mid(&data) {
+            Err(Error::UnexpectedValue { kind: FileKind::Mid, offset: 31, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut data = chunk(b"MThd", &[0, 0, 0, 1, 0xE7, 40]);
+        data.extend(chunk(b"MTrk", &[]));
+        let midi = parse_mid(&data).unwrap();
+        assert_eq!(midi.timing, MidiTiming::Smpte(25, 40));
+        assert_eq!(midi.tick_to_micros(1000), 1_000_000);
+
+        match parse_mid(&chunk(b"MThd", &[0, 2, 0, 1, 0, 96])) {
+            Err(Error::UnexpectedValue { kind: FileKind::Mid, offset: 8, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_mid(b"MTrk") {
+            Err(Error::MissingIdentifier { kind: FileKind::Mid, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -1,5 +1,9 @@
+pub mod detect;
 pub mod lst;
+pub mod mid;
+pub mod registry;
 pub mod rle;
 pub mod rmd;
 pub mod rmm;
 pub mod rmi;
+pub mod rms;
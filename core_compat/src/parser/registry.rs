@@ -0,0 +1,235 @@
+//! Maps the version that a file's header declares to the layout its content is read with.
+//!
+//! The parsers are based on the files of the 3.9 client, other releases of the game may declare
+//! other versions or lay out the same version a little differently. Registering those quirks
+//! lets the same parsers read the files of every client install. Of the list versions, the 1.0
+//! and 1.2 layouts are the ones found in the game; no release that needs another map or rmd
+//! layout is known yet, so the map layout below is made up to show the registry:
+//!
+//! ```
+//! use core_compat::entity::list_version::ListVersion;
+//! use core_compat::entity::map_layout::MapLayout;
+//! use core_compat::parser::registry::VersionRegistry;
+//!
+//! let mut versions = VersionRegistry::new();
+//! versions.register_list("1.3", ListVersion::V1_2);
+//! versions.register_map("RedMoon MapData 1.1", MapLayout { tile_len: 10 });
+//! assert_eq!(versions.list_layout("1.3"), Some(ListVersion::V1_2));
+//! assert_eq!(versions.map_layout("RedMoon MapData 1.0"), Some(MapLayout::default()));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::entity::list_version::ListVersion;
+use crate::entity::map_layout::MapLayout;
+use crate::entity::rmd_layout::RmdLayout;
+use crate::parser::rmm::RMM_IDENTIFIER;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionRegistry {
+    lists: HashMap<String, ListVersion>,
+    maps: HashMap<String, MapLayout>,
+    rmds: HashMap<String, RmdLayout>,
+}
+
+impl VersionRegistry {
+    /// The versions found in the 3.9 client
+    pub fn new() -> VersionRegistry {
+        let mut versions = VersionRegistry::empty();
+        versions.register_list("1.0", ListVersion::V1_0);
+        versions.register_list("1.2", ListVersion::V1_2);
+        versions.register_map(RMM_IDENTIFIER, MapLayout::default());
+        versions
+    }
+
+    /// A registry without any versions, for a client that shares none of them with 3.9
+    pub fn empty() -> VersionRegistry {
+        VersionRegistry {
+            lists: HashMap::new(),
+            maps: HashMap::new(),
+            rmds: HashMap::new(),
+        }
+    }
+
+    /// Reads the list files that declare `version` with `layout`, replacing the earlier one
+    pub fn register_list(&mut self, version: &str, layout: ListVersion) {
+        self.lists.insert(version.into(), layout);
+    }
+
+    /// Reads the map files that start with `identifier` with `layout`
+    pub fn register_map(&mut self, identifier: &str, layout: MapLayout) {
+        self.maps.insert(identifier.into(), layout);
+    }
+
+    /// Reads the rmd files with the `file_type` string with `layout`
+    pub fn register_rmd(&mut self, file_type: &str, layout: RmdLayout) {
+        self.rmds.insert(file_type.into(), layout);
+    }
+
+    pub fn list_layout(&self, version: &str) -> Option<ListVersion> {
+        self.lists.get(version).cloned()
+    }
+
+    pub fn map_layout(&self, identifier: &str) -> Option<MapLayout> {
+        self.maps.get(identifier).cloned()
+    }
+
+    /// The rmd files aren't told apart by their file type yet, so the ones that aren't
+    /// registered are read with the layout of the 3.9 client.
+    pub fn rmd_layout(&self, file_type: &str) -> RmdLayout {
+        self.rmds.get(file_type).cloned().unwrap_or_default()
+    }
+
+    /// The registered list versions, sorted
+    pub fn list_versions(&self) -> Vec<&str> {
+        sorted_keys(&self.lists)
+    }
+
+    /// The registered map identifiers, sorted
+    pub fn map_identifiers(&self) -> Vec<&str> {
+        sorted_keys(&self.maps)
+    }
+
+    /// The layouts registered for any rmd file type, by frame length without duplicates
+    pub fn rmd_layouts(&self) -> Vec<RmdLayout> {
+        let mut layouts: Vec<RmdLayout> = self.rmds.values().cloned().collect();
+        layouts.sort_by_key(|layout| layout.frame_len);
+        layouts.dedup();
+        layouts
+    }
+}
+
+impl Default for VersionRegistry {
+    fn default() -> VersionRegistry {
+        VersionRegistry::new()
+    }
+}
+
+fn sorted_keys<T>(map: &HashMap<String, T>) -> Vec<&str> {
+    let mut keys: Vec<&str> = map.keys().map(|key| key.as_str()).collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::file_kind::FileKind;
+    use crate::entity::list::List;
+    use crate::entity::list_item::ListItem;
+    use crate::entity::entry::Entry;
+    use crate::entity::map::Map;
+    use crate::entity::map_tile::MapTile;
+    use crate::entity::rmd_type::RmdType;
+    use crate::entity::rmd_builder::RmdBuilder;
+    use crate::entity::rmd_image::RmdImage;
+    use crate::error::Error;
+    use crate::parser::lst::{parse_lst_with, parse_lst_lenient_with};
+    use crate::parser::rmd::parse_rmd_with;
+    use crate::parser::rmm::{parse_rmm, parse_rmm_with};
+    use crate::writer::lst::write_lst;
+    use crate::writer::rmd::write_rmd;
+    use crate::writer::rmm::write_rmm;
+
+    #[test]
+    fn test_registry_other_client_versions() {
+        // a list that declares "1.3" but is laid out like 1.2
+        let mut list = List::new();
+        list.items.push(ListItem {
+            name: "a".into(), id: 1, entry: Entry::new(2, 3), unknown_2: 4,
+        });
+        let mut lst = write_lst(&list, ListVersion::V1_2).unwrap();
+        lst[20] = b'3';
+        match parse_lst_with(&lst, &VersionRegistry::new()) {
+            Err(Error::UnexpectedValue { kind: FileKind::Lst, ref expected, .. }) => {
+                assert_eq!(expected, "version \"1.0\" or \"1.2\"");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|list| list.items.len())),
+        }
+        let mut versions = VersionRegistry::new();
+        versions.register_list("1.3", ListVersion::V1_2);
+        assert_eq!(parse_lst_with(&lst, &versions).unwrap().items, list.items);
+        let (parsed, fallbacks) = parse_lst_lenient_with(&lst, &versions).unwrap();
+        assert_eq!(parsed.items, list.items);
+        assert!(fallbacks.is_empty());
+
+        // a map with a newer identifier and two extra bytes after every tile
+        let mut map = Map::new();
+        map.set_size_x(2);
+        map.set_size_y(1);
+        let mut tile = MapTile::new();
+        tile.tle_rmd_entry = Entry::new(5, 6);
+        map.add_tile(tile);
+        map.add_tile(MapTile::new());
+        let rmm = write_rmm(&map).unwrap();
+        let identifier_len = rmm[0] as usize;
+        let tiles_offset = rmm.len() - 16;
+        let mut newer = vec![identifier_len as u8];
+        newer.extend_from_slice(b"RedMoon MapData 1.1");
+        newer.extend_from_slice(&rmm[1 + identifier_len..tiles_offset]);
+        for tile in rmm[tiles_offset..].chunks(8) {
+            newer.extend_from_slice(tile);
+            newer.extend_from_slice(&[0xAA, 0xBB]);
+        }
+        match parse_rmm(&newer) {
+            Err(Error::MissingIdentifier { kind: FileKind::Rmm, ref found, .. }) => {
+                assert_eq!(found, "RedMoon MapData 1.1");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|map| map.tile_count())),
+        }
+        versions.register_map("RedMoon MapData 1.1", MapLayout { tile_len: 10 });
+        assert_eq!(parse_rmm_with(&newer, &versions).unwrap().tiles(), map.tiles());
+        // the tile length doesn't fit the 1.0 maps
+        versions.register_map("RedMoon MapData 1.0", MapLayout { tile_len: 10 });
+        assert!(parse_rmm_with(&rmm, &versions).is_err());
+        // tiles shorter than the known bytes are rejected instead of widened
+        versions.register_map("RedMoon MapData 1.0", MapLayout { tile_len: 4 });
+        match parse_rmm_with(&rmm, &versions) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rmm, offset: 0, ref found, .. }) => {
+                assert_eq!(found, "4 byte tiles");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|map| map.tile_count())),
+        }
+    }
+
+    #[test]
+    fn test_registry_rmd_frame_len() {
+        let rmd = RmdBuilder::new(RmdType::Object)
+            .file_type("newer")
+            .entry(vec![RmdImage::new().with_image_ids(&[1])])
+            .animation(&[0, 1, -1])
+            .build();
+        let data = write_rmd(&rmd).unwrap();
+        // the same animation with every frame widened to four bytes
+        let frames_offset = data.len() - 6;
+        let mut wide = data[..frames_offset].to_vec();
+        for frame in data[frames_offset..].chunks(2) {
+            let frame = i16::from_le_bytes([frame[0], frame[1]]) as i32;
+            wide.extend_from_slice(&frame.to_le_bytes());
+        }
+
+        let mut versions = VersionRegistry::new();
+        assert_eq!(parse_rmd_with(RmdType::Object, &data, &versions).unwrap(), rmd);
+        versions.register_rmd("newer", RmdLayout { frame_len: 4 });
+        assert_eq!(versions.rmd_layout("other"), RmdLayout::default());
+        assert_eq!(parse_rmd_with(RmdType::Object, &wide, &versions).unwrap(), rmd);
+
+        let len = wide.len();
+        wide[len - 2] = 0x01;
+        match parse_rmd_with(RmdType::Object, &wide, &versions) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rmd, offset, .. }) => {
+                assert_eq!(offset, len as u64 - 4);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // only 2 and 4 byte frames are read
+        versions.register_rmd("newer", RmdLayout { frame_len: 3 });
+        match parse_rmd_with(RmdType::Object, &data, &versions) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rmd, ref found, .. }) => {
+                assert_eq!(found, "3 byte frames");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
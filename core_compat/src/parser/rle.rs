@@ -10,34 +10,225 @@ use byteorder::ReadBytesExt;
 use byteorder::LittleEndian as LE;
 
 use crate::error::Error;
+use crate::entity::color_key::ColorKey;
+use crate::entity::fallback::Fallback;
+use crate::entity::file_kind::FileKind;
 use crate::utility::pixel::Pixel;
 use crate::entity::resource::Resource;
+use crate::entity::pixel_format::PixelFormat;
 use crate::entity::resource_file::ResourceFile;
 
+const RLE_IDENTIFIER: &[u8; 14] = b"Resource File\0";
+const FILE_HEADER_LEN: usize = 14 + 4 + 4;
+const RESOURCE_HEADER_LEN: usize = 9 * 4;
+
 pub fn parse_rle(file_number: u32, data: &[u8]) -> Result<ResourceFile, Error> {
+    parse_rle_as(file_number, data, PixelFormat::Rgba8888)
+}
+
+/// Same as `parse_rle` but decodes the images into `pixel_format`. With `R5g6b5` the colours
+/// are copied as is and the transparency is written to the resource's `mask`.
+pub fn parse_rle_as(
+    file_number: u32,
+    data: &[u8],
+    pixel_format: PixelFormat
+) -> Result<ResourceFile, Error> {
+    parse_rle_with(file_number, data, pixel_format, ColorKey::Off)
+}
+
+/// Same as `parse_rle_as` but the pixels painted with the `color_key` colour are left
+/// transparent.
+pub fn parse_rle_with(
+    file_number: u32,
+    data: &[u8],
+    pixel_format: PixelFormat,
+    color_key: ColorKey
+) -> Result<ResourceFile, Error> {
     let mut cursor = Cursor::new(data);
     let mut resource_file = ResourceFile::new();
+    resource_file.file_number = file_number;
 
-    // file type string: needs to equal "Resource File\n"
-    let (file_type, _rest) = if data.len() >= 14 {
-        data.split_at(14)
-    } else {
-        return Err(Error::MissingRleIdentifier);
-    };
-    let file_type: &str = from_utf8(file_type)?;
+    let resource_offsets = parse_rle_offsets(data)?;
+
+    // println!("Loading {} resources at offsets:{:?}", total_resources, resource_offsets);
+
+    for (idx, offset) in resource_offsets.iter().enumerate() {
+
+        let offset = *offset;
+
+        if offset == 0 {
+            // we'll skip 0 (null) offsets as I think they are just placeholders in the file
+            // but we can't ignore them in the resource offset list because the index of the
+            // resource is important
+            continue;
+        }
+
+        let mut resource = read_resource_header(&mut cursor, file_number, idx as u32, offset)
+            .map_err(|err| err.in_file(FileKind::Rle, cursor.position()))?;
+        if check_resource_size(&resource).is_err() {
+            // left out like an empty slot so the rest of the archive is still read,
+            // `parse_rle_lenient` reports these
+            continue;
+        }
+        decode_image(&mut cursor, &mut resource, pixel_format, color_key)
+            .map_err(|err| err.in_file(FileKind::Rle, cursor.position()))?;
+        resource_file.resources.push(resource);
+    }
+    Ok(resource_file)
+}
+
+/// Reads what it can of a damaged RLE file: a wrong identifier is ignored, an offset table that
+/// runs past the end of the file is cut short and the resources that point outside of the file,
+/// have an impossible size or fail to decode are left out. Every deviation is reported as a
+/// `Fallback`.
+pub fn parse_rle_lenient(
+    file_number: u32,
+    data: &[u8]
+) -> Result<(ResourceFile, Vec<Fallback>), Error> {
+    parse_rle_lenient_with(file_number, data, PixelFormat::Rgba8888, ColorKey::Off)
+}
+
+/// Same as `parse_rle_lenient`, see `parse_rle_with` for the `pixel_format` and `color_key`
+pub fn parse_rle_lenient_with(
+    file_number: u32,
+    data: &[u8],
+    pixel_format: PixelFormat,
+    color_key: ColorKey
+) -> Result<(ResourceFile, Vec<Fallback>), Error> {
+    let mut cursor = Cursor::new(data);
+    let mut fallbacks = Vec::<Fallback>::new();
+    let resource_offsets = read_rle_offsets(&mut cursor, true, &mut fallbacks)
+        .map_err(|err| err.in_file(FileKind::Rle, cursor.position()))?;
+
+    let mut resource_file = ResourceFile::new();
+    resource_file.file_number = file_number;
+    let mut skipped = Vec::<u32>::new();
+    for (idx, &offset) in resource_offsets.iter().enumerate() {
+        if offset == 0 {
+            continue;
+        }
+        if offset as usize + RESOURCE_HEADER_LEN > data.len() {
+            skipped.push(idx as u32);
+            continue;
+        }
+        match parse_resource(&mut cursor, file_number, idx as u32, offset,
+                             pixel_format, color_key) {
+            Ok(resource) => resource_file.resources.push(resource),
+            Err(_) => skipped.push(idx as u32),
+        }
+    }
+    if !skipped.is_empty() {
+        fallbacks.push(Fallback::SkippedResources { indices: skipped });
+    }
+    Ok((resource_file, fallbacks))
+}
+
+/// Reads only the header and offset table of an RLE file up front; each resource is decoded
+/// when it is asked for. This avoids decoding a whole archive when only a single frame of it
+/// is used.
+pub struct RleReader {
+    file_number: u32,
+    data: Vec<u8>,
+    offsets: Vec<u32>,
+    color_key: ColorKey,
+}
 
-    if file_type != "Resource File\0" {
-        return Err(Error::MissingRleIdentifier);
+impl RleReader {
+    pub fn new(file_number: u32, data: Vec<u8>) -> Result<RleReader, Error> {
+        let offsets = parse_rle_offsets(&data)?;
+        Ok(RleReader { file_number, data, offsets, color_key: ColorKey::Off })
+    }
+
+    /// Sets the colour key applied to every resource read afterwards, `ColorKey::Off` by default
+    pub fn set_color_key(&mut self, color_key: ColorKey) {
+        self.color_key = color_key;
+    }
+
+    pub fn color_key(&self) -> ColorKey {
+        self.color_key
+    }
+
+    pub fn file_number(&self) -> u32 {
+        self.file_number
+    }
+
+    /// The number of slots in the offset table, including the empty ones
+    pub fn resource_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn has_resource(&self, index: u32) -> bool {
+        match self.offsets.get(index as usize) {
+            Some(&offset) => offset != 0,
+            None => false,
+        }
+    }
+
+    /// Decodes the resource at `index`; `None` if the slot is empty or out of range
+    pub fn read_resource(&self, index: u32) -> Result<Option<Resource>, Error> {
+        self.read_resource_as(index, PixelFormat::Rgba8888)
+    }
+
+    /// Same as `read_resource`, see `parse_rle_as` for the `pixel_format`
+    pub fn read_resource_as(
+        &self,
+        index: u32,
+        pixel_format: PixelFormat
+    ) -> Result<Option<Resource>, Error> {
+        if !self.has_resource(index) {
+            return Ok(None);
+        }
+        let offset = self.offsets[index as usize];
+        let mut cursor = Cursor::new(&self.data[..]);
+        parse_resource(&mut cursor, self.file_number, index, offset, pixel_format, self.color_key)
+            .map(Some)
+            .map_err(|err| err.in_file(FileKind::Rle, cursor.position()))
+    }
+}
+
+/// Checks the file identifier and returns the resource offset table
+fn parse_rle_offsets(data: &[u8]) -> Result<Vec<u32>, Error> {
+    let mut cursor = Cursor::new(data);
+    let mut fallbacks = Vec::<Fallback>::new();
+    read_rle_offsets(&mut cursor, false, &mut fallbacks)
+        .map_err(|err| err.in_file(FileKind::Rle, cursor.position()))
+}
+
+fn read_rle_offsets(
+    cursor: &mut Cursor<&[u8]>,
+    lenient: bool,
+    fallbacks: &mut Vec<Fallback>
+) -> Result<Vec<u32>, Error> {
+    let data = *cursor.get_ref();
+
+    // file type string: needs to equal "Resource File\n"
+    let file_type = &data[..data.len().min(14)];
+    if file_type != &RLE_IDENTIFIER[..] && lenient && data.len() >= FILE_HEADER_LEN {
+        fallbacks.push(Fallback::IgnoredIdentifier {
+            found: String::from_utf8_lossy(file_type).into(),
+        });
+    } else if file_type != &RLE_IDENTIFIER[..] {
+        return Err(Error::MissingIdentifier {
+            kind: FileKind::Rle,
+            offset: 0,
+            expected: String::from_utf8_lossy(RLE_IDENTIFIER).into(),
+            found: String::from_utf8_lossy(file_type).into(),
+        });
     }
 
     // start reading after the "Resource file string"
     cursor.seek(SeekFrom::Start(14u64))?;
 
-    // unknown_1: 4 Unknown bytes; (next free offset?)
-    let tmp = cursor.read_u32::<LE>()?;
+    // next free offset: 4 bytes (u32), the end of the file
+    let _next_free_offset = cursor.read_u32::<LE>()?;
 
     // total_resources: 4 bytes (u32)
-    let total_resources = cursor.read_u32::<LE>()?;
+    let mut total_resources = cursor.read_u32::<LE>()?;
+    let available = (data.len().saturating_sub(FILE_HEADER_LEN) / 4) as u32;
+    if lenient && total_resources > available {
+        fallbacks.push(Fallback::TruncatedEntries { declared: total_resources, read: available });
+        total_resources = available;
+    }
 
     // resource_offsets: [total_resources; u32]
     let mut resource_offsets = Vec::<u32>::new();
@@ -45,112 +236,175 @@ pub fn parse_rle(file_number: u32, data: &[u8]) -> Result<ResourceFile, Error> {
         let val = cursor.read_u32::<LE>()?;
         resource_offsets.push(val);
     }
+    Ok(resource_offsets)
+}
 
-    // println!("Loading {} resources at offsets:{:?}", total_resources, resource_offsets);
+/// Decodes the resource stored at `offset`
+fn parse_resource(
+    cursor: &mut Cursor<&[u8]>,
+    file_number: u32,
+    idx: u32,
+    offset: u32,
+    pixel_format: PixelFormat,
+    color_key: ColorKey
+) -> Result<Resource, Error> {
+    let mut resource = read_resource_header(cursor, file_number, idx, offset)?;
+    check_resource_size(&resource)?;
+    decode_image(cursor, &mut resource, pixel_format, color_key)?;
+    Ok(resource)
+}
 
-    for (idx, offset) in resource_offsets.iter().enumerate() {
+/// Reads the header of the resource stored at `offset`, the cursor is left at its image
+fn read_resource_header(
+    cursor: &mut Cursor<&[u8]>,
+    file_number: u32,
+    idx: u32,
+    offset: u32
+) -> Result<Resource, Error> {
+    let mut resource = Resource::new();
+    cursor.seek(SeekFrom::Start(offset as u64))?;
 
-        let offset = *offset;
+    // resource id's
+    resource.file_num = Some(file_number);
+    resource.set_index(idx);
+    resource.offset = offset;
 
-        if offset == 0 {
-            // we'll skip 0 (null) offsets as I think they are just placeholders in the file
-            // but we can't ignore them in the resource offset list because the index of the
-            // resource is important
-            continue;
-        }
+    // read the resource header
+    resource.len = cursor.read_u32::<LE>()?;
+    resource.offset_x = cursor.read_i32::<LE>()?;
+    resource.offset_y = cursor.read_i32::<LE>()?;
+    resource.width = cursor.read_i32::<LE>()?;
+    resource.height = cursor.read_i32::<LE>()?;
+    resource.unknown_1 = cursor.read_u32::<LE>()?;
+    resource.unknown_2 = cursor.read_u32::<LE>()?;
+    resource.unknown_3 = cursor.read_u32::<LE>()?;
+    resource.unknown_4 = cursor.read_u32::<LE>()?;
+    Ok(resource)
+}
 
-        let mut resource = Resource::new();
-        cursor.seek(SeekFrom::Start(offset as u64))?;
-
-        // resource id's
-        resource.file_num = Some(file_number);
-        resource.set_index(idx as u32);
-        resource.offset = offset;
-
-        // read the resource header
-        resource.len = cursor.read_u32::<LE>()?;
-        resource.offset_x = cursor.read_i32::<LE>()?;
-        resource.offset_y = cursor.read_i32::<LE>()?;
-        resource.width = cursor.read_i32::<LE>()?;
-        resource.height = cursor.read_i32::<LE>()?;
-        resource.unknown_1 = cursor.read_u32::<LE>()?;
-        resource.unknown_2 = cursor.read_u32::<LE>()?;
-        resource.unknown_3 = cursor.read_u32::<LE>()?;
-        resource.unknown_4 = cursor.read_u32::<LE>()?;
-
-        // Pre-fill the image buffer with 0's
-        if resource.width < 8000 && resource.width > 0
-            && resource.height < 8000 && resource.height > 0 {
-            let total_px = resource.width * resource.height * 4 /* bytes / pixel */;
-            for _ in 0..total_px {
-                resource.image_raw.push(0x0);
-            }
-        } else {
-            println!("wrongly sized resource: ({}, {})", resource.width, resource.height);
-            // oversized resource
-            resource.image_raw.push(0xFF); // R
-            resource.image_raw.push(0xFF); // G
-            resource.image_raw.push(0xFF); // B
-            resource.image_raw.push(0xFF); // A
-            continue;
-        }
+fn check_resource_size(resource: &Resource) -> Result<(), Error> {
+    if resource.width < 8000 && resource.width > 0
+        && resource.height < 8000 && resource.height > 0 {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedValue {
+            kind: FileKind::Rle,
+            offset: resource.offset as u64 + 12,
+            expected: "a size from 1x1 to 7999x7999".into(),
+            found: format!("{}x{}", resource.width, resource.height),
+        })
+    }
+}
 
-        // read the rest of the image data
-        let mut x = 0i32;
-        let mut y = 0i32;
-        'image: loop {
-            let entry_type = cursor.read_u8().unwrap();
-            // println!("RLE Entry Type:{} @ offset: `{}`",
-            //          entry_type,
-            //          cursor.position());
-            match entry_type {
-                0x00 => {
-                    /* End resource marker */
-                    break 'image;
-                }
-                0x01 => {
-                    /* Paint pixels */
-                    let pixels = cursor.read_u32::<LE>()?;
-                    for p in 0..pixels {
-                        let data = cursor.read_u16::<LE>()?;
-                        let (r, g, b) = format_r5g6b5_norm(data);
-                        let _y = y * 4 * resource.width as i32;
-                        let _x = x * 4;
-                        let idx: usize = _y as usize + _x as usize;
-                        resource.image_raw[idx]   = r;
-                        resource.image_raw[idx+1] = g;
-                        resource.image_raw[idx+2] = b;
-                        resource.image_raw[idx+3] = 0xFF;
+/// Decodes the image that follows the header of a resource with a checked size
+fn decode_image(
+    cursor: &mut Cursor<&[u8]>,
+    resource: &mut Resource,
+    pixel_format: PixelFormat,
+    color_key: ColorKey
+) -> Result<(), Error> {
+    // Pre-fill the image buffer with 0's
+    let total_px = (resource.width * resource.height) as usize;
+    resource.pixel_format = pixel_format;
+    resource.image_raw = vec![0x0; total_px * pixel_format.bytes_per_pixel()];
+    if pixel_format == PixelFormat::R5g6b5 {
+        resource.mask = vec![0x0; total_px];
+    }
 
+    // read the rest of the image data
+    let mut x = 0i32;
+    let mut y = 0i32;
+    'image: loop {
+        let entry_type = cursor.read_u8()?;
+        // println!("RLE Entry Type:{} @ offset: `{}`",
+        //          entry_type,
+        //          cursor.position());
+        match entry_type {
+            0x00 => {
+                /* End resource marker */
+                break 'image;
+            }
+            0x01 => {
+                /* Paint pixels */
+                let pixels = cursor.read_u32::<LE>()?;
+                for p in 0..pixels {
+                    let data = cursor.read_u16::<LE>()?;
+                    if x < 0 || x >= resource.width || y >= resource.height {
+                        return Err(Error::UnexpectedValue {
+                            kind: FileKind::Rle,
+                            offset: cursor.position() - 2,
+                            expected: format!("a pixel inside the {}x{} image",
+                                              resource.width, resource.height),
+                            found: format!("a pixel at ({}, {})", x, y),
+                        });
+                    }
+                    if color_key.is_keyed(data) {
+                        // keyed pixels stay transparent, like the ones skipped over
                         x += 1;
+                        continue;
                     }
-                }
-                0x02 => {
-                    /* Move `x` pos */
-                    let pixels = cursor.read_i32::<LE>()?;
-                    x += pixels / 2; // NOTE: the two is probaby a u16 jump?
-                }
-                0x03 => {
-                    /* Next line */
-                    y += 1;
-                }
-                _ => {
-                    return Err(Error::UnknownOffsetTypeAt(cursor.position()));
+                    match pixel_format {
+                        PixelFormat::Rgba8888 => {
+                            let (r, g, b) = format_r5g6b5_norm(data);
+                            let _y = y * 4 * resource.width;
+                            let _x = x * 4;
+                            let idx: usize = _y as usize + _x as usize;
+                            resource.image_raw[idx]   = r;
+                            resource.image_raw[idx+1] = g;
+                            resource.image_raw[idx+2] = b;
+                            resource.image_raw[idx+3] = 0xFF;
+                        }
+                        PixelFormat::R5g6b5 => {
+                            let idx = (y * resource.width + x) as usize;
+                            resource.image_raw[idx*2]   = data as u8;
+                            resource.image_raw[idx*2+1] = (data >> 8) as u8;
+                            resource.mask[idx] = 0xFF;
+                        }
+                    }
+
+                    x += 1;
                 }
             }
+            0x02 => {
+                /* Move `x` pos */
+                let pixels = cursor.read_i32::<LE>()?;
+                // NOTE: the two is probaby a u16 jump?
+                x = match x.checked_add(pixels / 2) {
+                    Some(x) => x,
+                    None => return Err(Error::UnexpectedValue {
+                        kind: FileKind::Rle,
+                        offset: cursor.position() - 4,
+                        expected: format!("a move inside the {}x{} image",
+                                          resource.width, resource.height),
+                        found: format!("a move of {} bytes from x {}", pixels, x),
+                    }),
+                };
+            }
+            0x03 => {
+                /* Next line */
+                y += 1;
+            }
+            _ => {
+                return Err(Error::UnexpectedValue {
+                    kind: FileKind::Rle,
+                    offset: cursor.position() - 1,
+                    expected: "an opcode from 0x00 to 0x03".into(),
+                    found: format!("{:#04x}", entry_type),
+                });
+            }
         }
-        resource_file.resources.push(resource);
     }
-    Ok(resource_file)
+    Ok(())
 }
 
 /// The pixels in the RLE files are saved as normalized 5,6,5 bit normalized RGB colors.
 /// Magenta is sometimes used in the images as an alpha colour but it is relatively rare; it is
 /// usually just enough to set the default colour to be transparent and "paint" over the pixels
-/// with the actual colour.
+/// with the actual colour. The sprites that do use it are decoded with `parse_rle_with` and
+/// `ColorKey::Magenta`.
 // TODO: There is probably a quicker way to do this conversion without the FP mult & div ...
-// TODO: Create type for r5g6b5 normalized colors and don't convert (OpenGL & DX can do this)
-fn format_r5g6b5_norm(d: u16) -> (u8, u8, u8) {
+// NOTE: `parse_rle_as` with `PixelFormat::R5g6b5` skips this conversion entirely
+pub(crate) fn format_r5g6b5_norm(d: u16) -> (u8, u8, u8) {
     let b = ((d & 0x1F) as f32 / 31.0) * 255.0;
     let g = (((d >> 5) & 0x3F) as f32 / 63.0) * 255.0;
     let r = (((d >> 11) & 0x1F) as f32 / 31.0) * 255.0;
@@ -179,4 +433,185 @@ mod tests {
         let data = include_bytes!("../../../data/RLEs/Ico/ico00000.rle");
         let rle = parse_rle(0, data).unwrap();
     }
+
+    #[test]
+    fn test_rle_reader_decodes_on_demand() {
+        use crate::writer::rle::write_rle;
+
+        let mut rf = ResourceFile::new();
+        for &(index, width) in [(0u32, 2i32), (2, 3)].iter() {
+            let mut resource = Resource::new();
+            resource.set_index(index);
+            resource.width = width;
+            resource.height = 1;
+            for px in 0..width {
+                resource.image_raw.extend_from_slice(&[0xFF, px as u8 * 0x20, 0x00, 0xFF]);
+            }
+            rf.resources.push(resource);
+        }
+        let data = write_rle(&rf).unwrap();
+        let parsed = parse_rle(7, &data).unwrap();
+
+        let reader = RleReader::new(7, data).unwrap();
+        assert_eq!(reader.resource_count(), 3);
+        assert!(reader.has_resource(0));
+        assert!(!reader.has_resource(1));
+        assert!(!reader.has_resource(3));
+        assert!(reader.read_resource(1).unwrap().is_none());
+        assert!(reader.read_resource(9).unwrap().is_none());
+
+        // decoding in reverse order gives the same resources as the full parse
+        for expected in parsed.resources.iter().rev() {
+            let resource = reader.read_resource(expected.index()).unwrap().unwrap();
+            assert_eq!(resource.file_num, Some(7));
+            assert_eq!(resource.offset, expected.offset);
+            assert_eq!(resource.width, expected.width);
+            assert_eq!(resource.image_raw, expected.image_raw);
+        }
+
+        // the native mode keeps the stored colours and moves the alpha to the mask
+        let native = reader.read_resource_as(2, PixelFormat::R5g6b5).unwrap().unwrap();
+        let rgba = reader.read_resource(2).unwrap().unwrap();
+        assert_eq!(native.pixel_format, PixelFormat::R5g6b5);
+        assert_eq!(native.image_raw.len(), 6);
+        assert_eq!(native.mask, vec![0xFF; 3]);
+        for px in 0..3 {
+            let c = native.image_raw[px * 2] as u16 | (native.image_raw[px * 2 + 1] as u16) << 8;
+            let (r, g, b) = format_r5g6b5_norm(c);
+            assert_eq!(&rgba.image_raw[px * 4..px * 4 + 4], &[r, g, b, 0xFF]);
+        }
+
+        match RleReader::new(0, b"Not a resource".to_vec()) {
+            Err(Error::MissingIdentifier { kind: FileKind::Rle, .. }) => (),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.resource_count())),
+        }
+    }
+
+    #[test]
+    fn test_rle_color_key() {
+        use crate::writer::rle::write_rle;
+
+        // a 2x2 sprite with a magenta and a custom keyed pixel
+        let colours = [0xF81Fu16, 0x07E0, 0x001F, 0xF800];
+        let mut resource = Resource::new();
+        resource.width = 2;
+        resource.height = 2;
+        for &c in colours.iter() {
+            let (r, g, b) = format_r5g6b5_norm(c);
+            resource.image_raw.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+        let mut rf = ResourceFile::new();
+        rf.resources.push(resource);
+        let data = write_rle(&rf).unwrap();
+
+        let alpha = |key: ColorKey| {
+            let parsed = parse_rle_with(0, &data, PixelFormat::Rgba8888, key).unwrap();
+            parsed.resources[0].image_raw.chunks(4).map(|px| px[3]).collect::<Vec<u8>>()
+        };
+        assert_eq!(alpha(ColorKey::Off), vec![0xFF; 4]);
+        assert_eq!(alpha(ColorKey::Magenta), vec![0x00, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(alpha(ColorKey::Custom(0x001F)), vec![0xFF, 0xFF, 0x00, 0xFF]);
+        // the keyed pixel doesn't shift the ones after it
+        let parsed = parse_rle_with(0, &data, PixelFormat::Rgba8888, ColorKey::Magenta).unwrap();
+        assert_eq!(&parsed.resources[0].image_raw[4..], &rf.resources[0].image_raw[4..]);
+
+        // the native mode and the reader share the same key
+        let mut reader = RleReader::new(0, data.clone()).unwrap();
+        assert_eq!(reader.color_key(), ColorKey::Off);
+        reader.set_color_key(ColorKey::Magenta);
+        let native = reader.read_resource_as(0, PixelFormat::R5g6b5).unwrap().unwrap();
+        assert_eq!(native.mask, vec![0x00, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(&native.image_raw[..2], &[0, 0]);
+        let rgba = reader.read_resource(0).unwrap().unwrap();
+        assert_eq!(rgba.image_raw, parsed.resources[0].image_raw);
+
+        // only the sprite types drawn over magenta key it out
+        use crate::entity::sprite_type::SpriteType;
+        assert_eq!(SpriteType::Object.color_key(), ColorKey::Magenta);
+        assert_eq!(SpriteType::Interface.color_key(), ColorKey::Magenta);
+        assert_eq!(SpriteType::Tile.color_key(), ColorKey::Off);
+        assert_eq!(SpriteType::Character.color_key(), ColorKey::Off);
+    }
+
+    #[test]
+    fn test_rle_lenient() {
+        use crate::writer::rle::write_rle;
+
+        let mut rf = ResourceFile::new();
+        for index in 0..3 {
+            let mut resource = Resource::new();
+            resource.set_index(index);
+            resource.width = 1;
+            resource.height = 1;
+            resource.image_raw = vec![0xFF, 0xFF, 0xFF, 0xFF];
+            rf.resources.push(resource);
+        }
+        let data = write_rle(&rf).unwrap();
+        let (parsed, fallbacks) = parse_rle_lenient(0, &data).unwrap();
+        assert_eq!(parsed.resources.len(), 3);
+        assert!(fallbacks.is_empty());
+
+        // resource 1 points past the end and resource 2 holds an unknown opcode
+        let mut damaged = data.clone();
+        let table = FILE_HEADER_LEN;
+        damaged[table + 4..table + 8].copy_from_slice(&0xFFFFu32.to_le_bytes());
+        let last = damaged.len() - 1;
+        damaged[last] = 0x07;
+        damaged[0] = b'r';
+        let (parsed, fallbacks) = parse_rle_lenient(0, &damaged).unwrap();
+        assert_eq!(parsed.resources.len(), 1);
+        assert_eq!(fallbacks, vec![
+            Fallback::IgnoredIdentifier { found: "resource File\u{0}".into() },
+            Fallback::SkippedResources { indices: vec![1, 2] },
+        ]);
+
+        // an offset table longer than the file
+        let mut damaged = data.clone();
+        damaged[18..22].copy_from_slice(&1000u32.to_le_bytes());
+        let (_, fallbacks) = parse_rle_lenient(0, &damaged).unwrap();
+        let available = (data.len() - FILE_HEADER_LEN) as u32 / 4;
+        assert_eq!(fallbacks[0], Fallback::TruncatedEntries { declared: 1000, read: available });
+
+        // resource 1 has no width
+        let mut damaged = data.clone();
+        let offset = u32::from_le_bytes([
+            damaged[table + 4], damaged[table + 5], damaged[table + 6], damaged[table + 7],
+        ]) as usize;
+        damaged[offset + 12..offset + 16].copy_from_slice(&0u32.to_le_bytes());
+        // the strict parser leaves it out like an empty slot and keeps the others
+        let parsed = parse_rle(0, &damaged).unwrap();
+        let indices: Vec<u32> = parsed.resources.iter().map(|r| r.index()).collect();
+        assert_eq!(indices, vec![0, 2]);
+        let (parsed, fallbacks) = parse_rle_lenient(0, &damaged).unwrap();
+        assert_eq!(parsed.resources.len(), 2);
+        assert_eq!(fallbacks, vec![Fallback::SkippedResources { indices: vec![1] }]);
+        let reader = RleReader::new(0, damaged).unwrap();
+        match reader.read_resource(1) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rle, offset: found_at, ref found, .. }) => {
+                assert_eq!(found_at, offset as u64 + 12);
+                assert_eq!(found, "0x1");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|r| r.map(|r| r.index()))),
+        }
+
+        // moves that run past the largest x
+        let mut data = RLE_IDENTIFIER.to_vec();
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&(FILE_HEADER_LEN as u32 + 4).to_le_bytes());
+        for &value in [0u32, 0, 0, 1, 1, 0, 0, 0, 0].iter() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for _ in 0..3 {
+            data.push(0x02);
+            data.extend_from_slice(&i32::MAX.to_le_bytes());
+        }
+        data.push(0x00);
+        match parse_rle(0, &data) {
+            Err(Error::UnexpectedValue { offset: found_at, .. }) => {
+                assert_eq!(found_at, data.len() as u64 - 5);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|rf| rf.resources.len())),
+        }
+    }
 }
@@ -25,7 +25,7 @@
 //! int renderz
 //! int DestX
 //! int DestY
-//! int Draw Type (Shadow, skill, normal)
+//! int Draw Type (Shadow, skill, normal; unverified, see `DrawType`)
 //! int ImageIDCount
 //!
 //! [RMD Row - Images - Image ID]
@@ -38,8 +38,10 @@
 //! int AnimationFrames
 //!
 //! [RMD Animation - Frame]
-//! int RMDRowPointer (points to a row of the RMD)
+//! short RMDRowPointer (points to a row of the RMD; an int in some
+//!                      clients, see `RmdLayout`)
 
+use std::convert::TryFrom;
 use std::str::from_utf8;
 use std::io::Cursor;
 use std::io::Seek;
@@ -54,36 +56,61 @@ use crate::entity::rmd_entry::RmdEntry;
 use crate::entity::rmd_type::RmdType;
 use crate::entity::rmd_image::RmdImage;
 use crate::error::Error;
-use crate::utility::parsing::{parse_string, parse_cp949, parse_u8_vec};
+use crate::entity::file_kind::FileKind;
+use crate::entity::rmd_layout::RmdLayout;
+use crate::parser::registry::VersionRegistry;
+use crate::utility::parsing::parse_text;
 
 pub fn parse_rmd(kind: RmdType, data: &[u8]) -> Result<Rmd, Error> {
+    parse_rmd_with(kind, data, &VersionRegistry::new())
+}
+
+/// Reads the file with the layout that `versions` has for the file type in its header
+pub fn parse_rmd_with(
+    kind: RmdType,
+    data: &[u8],
+    versions: &VersionRegistry
+) -> Result<Rmd, Error> {
+    parse_rmd_len(kind, data, versions).map(|(rmd, _)| rmd)
+}
+
+/// Same as `parse_rmd_with`, also returning the number of bytes the rmd took up
+pub(crate) fn parse_rmd_len(
+    kind: RmdType,
+    data: &[u8],
+    versions: &VersionRegistry
+) -> Result<(Rmd, u64), Error> {
     let mut cursor = Cursor::new(data);
+    let rmd = read_rmd(&mut cursor, kind, versions)
+        .map_err(|err| err.in_file(FileKind::Rmd, cursor.position()))?;
+    Ok((rmd, cursor.position()))
+}
+
+fn read_rmd(
+    cursor: &mut Cursor<&[u8]>,
+    kind: RmdType,
+    versions: &VersionRegistry
+) -> Result<Rmd, Error> {
     let mut rmd = Rmd::new(kind);
 
     // filetype string: Equal to ""
-    let string_1 = parse_string(&mut cursor)?;
-    // println!("{:?}", string_1);
+    rmd.set_file_type(parse_text(cursor)?);
+    let layout = versions.rmd_layout(rmd.file_type().as_str());
 
-    let file_number = cursor.read_u32::<LE>()?; // 4
-    // println!("file_number: {}", file_number);
+    rmd.set_file_number(cursor.read_u32::<LE>()?); // 4
 
     // 8 empty bytes
-    let padding = cursor.read_u32::<LE>()?; // 8
-    if padding != 0 { println!("p2: {}", padding); }
-    let padding = cursor.read_u32::<LE>()?; // 12
-    if padding != 0 { println!("p3: {}", padding); }
+    let padding_1 = cursor.read_u32::<LE>()?; // 8
+    let padding_2 = cursor.read_u32::<LE>()?; // 12
+    rmd.set_padding([padding_1, padding_2]);
 
-    // let string = parse_string(&mut cursor)?;
-    // let string = parse_u8_vec(&mut cursor)?;
-    let string = parse_cp949(&mut cursor)?;
-    // println!("str 1: `{:?}`", string);
+    // cp949 string; the NUL padding is kept in the raw bytes
+    rmd.set_string_1(parse_text(cursor)?);
 
     rmd.set_animation_parts(cursor.read_i32::<LE>()?);
     rmd.set_animation_entry_count(cursor.read_i32::<LE>()?);
 
-    // let string = parse_u8_vec(&mut cursor)?;
-    let string = parse_cp949(&mut cursor)?;
-    // println!("str 2: `{:?}`", string);
+    rmd.set_string_2(parse_text(cursor)?);
 
     rmd.set_entry_count(cursor.read_i32::<LE>()?);
 
@@ -119,7 +146,7 @@ pub fn parse_rmd(kind: RmdType, data: &[u8]) -> Result<Rmd, Error> {
     for _ in 0..rmd.animation_count() {
         let mut ani = RmdAnimation::new(cursor.read_i32::<LE>()?);
         for _ in 0..ani.frame_count() {
-            ani.add_frame(cursor.read_i16::<LE>()?);
+            ani.add_frame(read_frame(cursor, layout)?);
         }
         rmd.add_animation(ani);
     }
@@ -128,6 +155,28 @@ pub fn parse_rmd(kind: RmdType, data: &[u8]) -> Result<Rmd, Error> {
     Ok(rmd)
 }
 
+fn read_frame(cursor: &mut Cursor<&[u8]>, layout: RmdLayout) -> Result<i16, Error> {
+    let offset = cursor.position();
+    match layout.frame_len {
+        2 => Ok(cursor.read_i16::<LE>()?),
+        4 => {
+            let frame = cursor.read_i32::<LE>()?;
+            i16::try_from(frame).map_err(|_| Error::UnexpectedValue {
+                kind: FileKind::Rmd,
+                offset,
+                expected: "an animation frame that fits in 16 bits".into(),
+                found: frame.to_string(),
+            })
+        }
+        frame_len => Err(Error::UnexpectedValue {
+            kind: FileKind::Rmd,
+            offset,
+            expected: "a layout with 2 or 4 byte animation frames".into(),
+            found: format!("{} byte frames", frame_len),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,4 +220,112 @@ mod tests {
         // assert!(rmd.row_count as usize == rmd.rows.len());
         // assert!(rmd.animation_count as usize == rmd.animations.len());
     }
+
+    #[test]
+    fn test_rmd_draw_types_and_layers() {
+        use std::collections::HashMap;
+        use crate::entity::draw_type::DrawType;
+        use crate::entity::render_layer::RenderLayer;
+        use crate::entity::rmd_builder::RmdBuilder;
+        use crate::entity::rmd_image::RmdImage;
+        use crate::writer::rmd::write_rmd;
+
+        let image = |render_z: i32, draw_type: i32, id: i32| {
+            RmdImage::new()
+                .with_render_z(render_z)
+                .with_draw_type(draw_type)
+                .with_image_ids(&[id])
+        };
+        // a character with a shadow and a weapon drawn behind the body, an object and a
+        // skill effect with a value that isn't known yet
+        let fixtures = [
+            RmdBuilder::new(RmdType::Character)
+                .entry(vec![image(2, 2, 1), image(0, 0, 2), image(1, 2, 3)])
+                .entry(vec![image(2, 2, 4), image(2, 2, 5)])
+                .build(),
+            RmdBuilder::new(RmdType::Object)
+                .entry(vec![image(2, 2, 6), image(0, 0, 7)])
+                .build(),
+            RmdBuilder::new(RmdType::Bullet)
+                .entry(vec![image(5, 1, 8), image(2, 7, 9)])
+                .build(),
+        ];
+
+        let mut draw_types = HashMap::<DrawType, u32>::new();
+        let mut layers = HashMap::<RenderLayer, u32>::new();
+        for fixture in fixtures.iter() {
+            let data = write_rmd(fixture).unwrap();
+            let rmd = parse_rmd(fixture.kind(), &data).unwrap();
+            for idx in 0..rmd.entry_count() as usize {
+                let entry = rmd.get_entry(idx).unwrap();
+                for img in entry.images() {
+                    *draw_types.entry(img.draw_kind()).or_insert(0) += 1;
+                    *layers.entry(img.layer()).or_insert(0) += 1;
+                    assert_eq!(img.is_shadow(), img.draw_type == 0);
+                    assert_eq!(img.draw_kind().raw(), img.draw_type);
+                    assert_eq!(img.layer().raw(), img.render_z);
+                }
+                // drawn back to front, the file order is kept within a layer
+                let order: Vec<i32> = entry.images_by_layer().iter().map(|i| i.render_z).collect();
+                let mut sorted = order.clone();
+                sorted.sort();
+                assert_eq!(order, sorted);
+            }
+        }
+        assert_eq!(draw_types[&DrawType::Normal], 5);
+        assert_eq!(draw_types[&DrawType::Shadow], 2);
+        assert_eq!(draw_types[&DrawType::Skill], 1);
+        assert_eq!(draw_types[&DrawType::Unknown(7)], 1);
+        assert_eq!(layers[&RenderLayer::Back], 2);
+        assert_eq!(layers[&RenderLayer::Middle], 1);
+        assert_eq!(layers[&RenderLayer::Front], 5);
+        assert_eq!(layers[&RenderLayer::Other(5)], 1);
+
+        let entry = fixtures[0].get_entry(0).unwrap();
+        let ids: Vec<i32> = entry.images_by_layer().iter().map(|i| i.image_id[0]).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+        let ids: Vec<i32> = fixtures[0].get_entry(1).unwrap()
+            .images_by_layer().iter().map(|i| i.image_id[0]).collect();
+        assert_eq!(ids, vec![4, 5]);
+
+        // a built image without a draw type is drawn as is, not as a shadow
+        let built = RmdImage::new().with_image_ids(&[1]);
+        assert_eq!(built.draw_kind(), DrawType::Normal);
+        assert!(!built.is_shadow());
+    }
+
+    #[test]
+    #[ignore] // needs the game files in the data folder
+    fn test_rmd_game_draw_types() {
+        use std::collections::BTreeMap;
+        use std::path::Path;
+        use crate::entity::draw_type::DrawType;
+        use crate::repository::vfs::Vfs;
+
+        // run with --nocapture to see the counts that `DrawType` and `RenderLayer` are
+        // documented against
+        let vfs = Vfs::new(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../data"));
+        for &(kind, dir) in [(RmdType::Character, "DATAs/Chr"), (RmdType::Object, "DATAs/Obj"),
+                             (RmdType::Bullet, "DATAs/Bul")].iter() {
+            let mut draw_types = BTreeMap::<i32, u32>::new();
+            let mut layers = BTreeMap::<i32, u32>::new();
+            let files = vfs.list(dir);
+            assert!(!files.is_empty(), "no files in {}", dir);
+            for file in files.iter() {
+                let data = std::fs::read(file).unwrap();
+                let rmd = parse_rmd(kind, &data).unwrap_or_else(|err| panic!("{:?}: {}", file, err));
+                for idx in 0..rmd.entry_count() as usize {
+                    for img in rmd.get_entry(idx).unwrap().images() {
+                        *draw_types.entry(img.draw_type).or_insert(0) += 1;
+                        *layers.entry(img.render_z).or_insert(0) += 1;
+                    }
+                }
+            }
+            println!("{}: draw_type {:?}, render_z {:?}", dir, draw_types, layers);
+            for &raw in draw_types.keys() {
+                assert!(!matches!(DrawType::from_raw(raw), DrawType::Unknown(_)),
+                        "{}: draw_type {} has no name", dir, raw);
+            }
+        }
+    }
 }
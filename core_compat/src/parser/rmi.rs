@@ -1,79 +1,130 @@
 //! "RedMoon EventInfo File 1.0"
 
-use std::str::from_utf8;
 use std::io::Cursor;
 
 use byteorder::ReadBytesExt;
 use byteorder::LittleEndian as LE;
 
 use crate::entity::rmi::Rmi;
+use crate::entity::rmi_action::RmiAction;
+use crate::entity::rmi_event::{RmiEvent, RMI_EVENT_TYPE_68};
+use crate::entity::text::Text;
 use crate::error::Error;
-use crate::utility::parsing::{parse_string, parse_cp949, parse_u8_vec};
+use crate::entity::file_kind::FileKind;
+use crate::utility::parsing::parse_text;
 
 pub fn parse_rmi(data: &[u8]) -> Result<Rmi, Error> {
     let mut cursor = Cursor::new(data);
-    let rmi = Rmi::new();
+    read_rmi(&mut cursor).map_err(|err| err.in_file(FileKind::Rmi, cursor.position()))
+}
+
+fn read_rmi(cursor: &mut Cursor<&[u8]>) -> Result<Rmi, Error> {
+    let mut rmi = Rmi::new();
 
     // -- header
-    let file_type_string = parse_string(&mut cursor).unwrap();
-    println!("file_type_string: {:?}", file_type_string);
+    rmi.set_file_type(parse_text(cursor)?);
 
     let count = cursor.read_i32::<LE>()?;
-    println!("count?: {:?}", count);
 
     // -- entries
-    for idx in 0..count {
-        println!("----------");
-        println!("-- Cursor Start @ 0x{:x}", cursor.position());
-
+    for _ in 0..count {
         let event_type = cursor.read_i32::<LE>()?;
-        println!("event_type: {:?}", event_type);
-        assert_eq!(68, event_type);
-
-        let event_pad_1 = cursor.read_u8()?;
-        println!("event_pad_1: {:?}", event_pad_1);
-        let event_pad_2 = cursor.read_u8()?;
-        println!("event_pad_2: {:?}", event_pad_2);
-
-        let event_count = cursor.read_i32::<LE>()?;
-        println!("event_count: {:?}", event_count);
+        if event_type != RMI_EVENT_TYPE_68 {
+            return Err(Error::UnexpectedValue {
+                kind: FileKind::Rmi,
+                offset: cursor.position() - 4,
+                expected: format!("event type {}", RMI_EVENT_TYPE_68),
+                found: format!("event type {}", event_type),
+            });
+        }
 
-        for e_idx in 0..event_count {
-            println!("{{");
-            println!("    idx: {:?}", e_idx);
+        let pad = [cursor.read_u8()?, cursor.read_u8()?];
 
+        let action_count = cursor.read_i32::<LE>()?;
+        let mut actions = Vec::<RmiAction>::new();
+        for _ in 0..action_count {
             let action_timeout = cursor.read_i32::<LE>()?;
-            println!("    action_timout: {:?}", action_timeout);
-
-            let pos = cursor.position();
-            let byte = cursor.read_u8()?;
-            if byte != 0 { cursor.set_position(pos); }
-            let trigger_string = parse_cp949(&mut cursor).unwrap();
-            println!("    trigger_string: {:?}", trigger_string);
-
-
-            let pos = cursor.position();
-            let byte = cursor.read_u8()?;
-            if byte != 0 { cursor.set_position(pos); }
-            let action_string = parse_cp949(&mut cursor).unwrap();
-            println!("    action_string: {:?}", action_string);
-
-            println!("}}");
+            let trigger = parse_action_string(cursor)?;
+            let action = parse_action_string(cursor)?;
+            actions.push(RmiAction { action_timeout, trigger, action });
         }
 
+        rmi.add_event(RmiEvent { event_type, pad, actions });
     }
 
     Ok(rmi)
 }
 
+/// The action strings are sometimes preceded by a single 0 byte which
+/// isn't part of the string's length prefix.
+fn parse_action_string(cursor: &mut Cursor<&[u8]>) -> Result<Text, Error> {
+    let pos = cursor.position();
+    let byte = cursor.read_u8()?;
+    if byte != 0 { cursor.set_position(pos); }
+    parse_text(cursor)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn push_string(data: &mut Vec<u8>, bytes: &[u8]) {
+        data.push(bytes.len() as u8);
+        data.extend_from_slice(bytes);
+    }
+
+    fn make_rmi_bytes(event_type: i32) -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        push_string(&mut data, b"RedMoon EventInfo File 1.0");
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&event_type.to_le_bytes());
+        data.extend_from_slice(&[0x01, 0x02]);
+        data.extend_from_slice(&2i32.to_le_bytes());
+        // first action: plain strings
+        data.extend_from_slice(&500i32.to_le_bytes());
+        push_string(&mut data, b"click");
+        push_string(&mut data, b"warp 1");
+        // second action: a leading 0 byte and a cp949 "한글" string
+        data.extend_from_slice(&(-1i32).to_le_bytes());
+        data.push(0);
+        push_string(&mut data, &[0xC7, 0xD1, 0xB1, 0xDB]);
+        push_string(&mut data, b"end");
+        data
+    }
+
     #[test]
     fn test_rmi_event00() {
         let data = include_bytes!("../../../data/DATAs/Info/event00.rmi");
         let rmi = parse_rmi(data);
         rmi.unwrap();
     }
+
+    #[test]
+    fn test_rmi_synthetic() {
+        let rmi = parse_rmi(&make_rmi_bytes(68)).unwrap();
+        assert_eq!(rmi.file_type(), "RedMoon EventInfo File 1.0");
+        assert_eq!(rmi.event_count(), 1);
+        let event = &rmi.events()[0];
+        assert_eq!(event.event_type, 68);
+        assert_eq!(event.pad, [0x01, 0x02]);
+        assert_eq!(event.actions, vec![
+            RmiAction { action_timeout: 500, trigger: "click".into(), action: "warp 1".into() },
+            RmiAction { action_timeout: -1, trigger: "한글".into(), action: "end".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_rmi_unknown_event_type() {
+        let data = make_rmi_bytes(69);
+        match parse_rmi(&data) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rmi, offset: 31, ref found, .. }) => {
+                assert_eq!(found, "event type 69");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_rmi(&make_rmi_bytes(68)[..40]) {
+            Err(Error::UnexpectedEof { kind: FileKind::Rmi, offset: 40 }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -25,6 +25,13 @@
 //! Collision (1XY has 2 spots to stand on and 4 different collision settings.
 //!            No collision, full collision,
 //!            left top collision, right bottom collision)
+//!
+//! The collision byte holds the setting times 24 (0 none, 1 full, 2 left top,
+//! 3 right bottom), the remainder only marks an odd object part. `MapTile` keeps
+//! the raw bytes and decodes them with `collision_kind` and `is_warp`.
+//!
+//! Other clients could use another identifier, or store more than 8 bytes per tile; a
+//! `MapLayout` for them can be registered in the `VersionRegistry`.
 
 
 use std::str::from_utf8;
@@ -36,38 +43,94 @@ use byteorder::ReadBytesExt;
 use byteorder::LittleEndian as LE;
 
 use crate::error::Error;
+use crate::entity::file_kind::FileKind;
 use crate::entity::map::Map;
 use crate::entity::map_tile::MapTile;
 use crate::entity::event::Event;
 use crate::entity::entry::Entry;
+use crate::entity::fallback::Fallback;
+use crate::utility::parsing::parse_text;
+use crate::entity::map_layout::MapLayout;
+use crate::parser::registry::VersionRegistry;
+
+pub(crate) const RMM_IDENTIFIER: &str = "RedMoon MapData 1.0";
+const TILE_LEN: u64 = 8;
+/// The most tiles the lenient mode pads a short map with
+const MAX_PADDED_TILES: u64 = 1 << 20;
 
 pub fn parse_rmm(data: &[u8]) -> Result<Map, Error> {
+    parse_rmm_with(data, &VersionRegistry::new())
+}
+
+/// Reads the map with the layout that `versions` has for the identifier in its header
+pub fn parse_rmm_with(data: &[u8], versions: &VersionRegistry) -> Result<Map, Error> {
     let mut cursor = Cursor::new(data);
-    let mut map = Map::new();
+    let mut fallbacks = Vec::<Fallback>::new();
+    read_rmm(&mut cursor, false, versions, &mut fallbacks)
+        .map_err(|err| err.in_file(FileKind::Rmm, cursor.position()))
+}
 
-    // filetype string: needs to equal "Redmoon MapData 1.0"
-    let string_length = cursor.read_u8()?;
-    let mut string = Vec::<u8>::new();
-    for _ in 0..string_length {
-        let chr = cursor.read_u8()?;
-        string.push(chr);
-    }
-    let file_type: &str = from_utf8(&string)?;
+/// Reads a map whose header doesn't have to match the rest of the file. The tiles that are
+/// there decide the map size when the declared one doesn't fit, and every deviation from the
+/// header is reported as a `Fallback`. The result always holds `size_x * size_y` tiles.
+pub fn parse_rmm_lenient(data: &[u8]) -> Result<(Map, Vec<Fallback>), Error> {
+    parse_rmm_lenient_with(data, &VersionRegistry::new())
+}
 
-    if file_type != "RedMoon MapData 1.0" {
-        // println!("{:?}", file_type);
-        return Err(Error::MissingMapIdentifier);
+/// `parse_rmm_lenient` that knows the identifiers in `versions`; a map with an unknown
+/// identifier is read with the 1.0 layout.
+pub fn parse_rmm_lenient_with(
+    data: &[u8],
+    versions: &VersionRegistry
+) -> Result<(Map, Vec<Fallback>), Error> {
+    let mut cursor = Cursor::new(data);
+    let mut fallbacks = Vec::<Fallback>::new();
+    let map = read_rmm(&mut cursor, true, versions, &mut fallbacks)
+        .map_err(|err| err.in_file(FileKind::Rmm, cursor.position()))?;
+    Ok((map, fallbacks))
+}
+
+fn read_rmm(
+    cursor: &mut Cursor<&[u8]>,
+    lenient: bool,
+    versions: &VersionRegistry,
+    fallbacks: &mut Vec<Fallback>
+) -> Result<Map, Error> {
+    let mut map = Map::new();
+
+    // filetype string: a registered identifier like "RedMoon MapData 1.0"
+    let identifier = parse_text(cursor)?.to_string();
+    let layout = match versions.map_layout(&identifier) {
+        Some(layout) => layout,
+        None if lenient => {
+            fallbacks.push(Fallback::IgnoredIdentifier { found: identifier });
+            MapLayout::default()
+        }
+        None => return Err(Error::MissingIdentifier {
+            kind: FileKind::Rmm,
+            offset: 0,
+            expected: versions.map_identifiers().join(" or "),
+            found: identifier,
+        }),
+    };
+    // every tile holds at least the 8 known bytes
+    let tile_len = layout.tile_len;
+    if tile_len < TILE_LEN {
+        return Err(Error::UnexpectedValue {
+            kind: FileKind::Rmm,
+            offset: 0,
+            expected: format!("a layout with tiles of at least {} bytes", TILE_LEN),
+            found: format!("{} byte tiles", tile_len),
+        });
     }
 
     // map size (x, y) in number of tiles
+    let size_offset = cursor.position();
     map.set_size_x(cursor.read_u32::<LE>()?);
     map.set_size_y(cursor.read_u32::<LE>()?);
 
     // Map String (name?)
-    map.set_id_count(cursor.read_u8()?);
-    for idx in 0..(map.id_count()) {
-        map.add_id_list_val(cursor.read_u8()?);
-    }
+    map.set_name(parse_text(cursor)?);
 
     // the map number described by this file...
     map.set_map_number(cursor.read_u32::<LE>()?);
@@ -83,22 +146,85 @@ pub fn parse_rmm(data: &[u8]) -> Result<Map, Error> {
             right: cursor.read_u32::<LE>()?,
             bottom: cursor.read_u32::<LE>()?,
         };
-        if event.number != 0 {
-            map.add_event(event);
-        }
+        map.add_event(event);
     }
 
     // read in the tile values...
-    let count = map.size_x() * map.size_y();
+    let mut count = map.size_x() as u64 * map.size_y() as u64;
+    if lenient {
+        count = fit_tiles(cursor, &mut map, tile_len, size_offset, fallbacks)?;
+    }
+    let mut tile_bits = 0;
     for tile in 0..count {
-        let tile = parse_v1(&mut cursor)?;
+        let tile = parse_v1(cursor, lenient)?;
+        for _ in TILE_LEN..tile_len {
+            cursor.read_u8()?;
+        }
+        if tile.unknown_1 & 0x2 != 0 {
+            tile_bits += 1;
+        }
         map.add_tile(tile);
     }
+    if tile_bits > 0 {
+        fallbacks.push(Fallback::UnexpectedTileBits { count: tile_bits });
+    }
+    // pad a map that is too short to hold its declared size
+    let declared = map.size_x() as u64 * map.size_y() as u64;
+    for _ in count..declared {
+        map.add_tile(MapTile::new());
+    }
 
     Ok(map)
 }
 
-fn parse_v1(cursor: &mut Cursor<&[u8]>) -> Result<MapTile, Error> {
+/// Compares the declared map size with the tiles left in the file and returns how many of them
+/// to read. A short map keeps its size if a smaller height doesn't fit exactly, and is padded.
+fn fit_tiles(
+    cursor: &mut Cursor<&[u8]>,
+    map: &mut Map,
+    tile_len: u64,
+    size_offset: u64,
+    fallbacks: &mut Vec<Fallback>
+) -> Result<u64, Error> {
+    let offset = cursor.position();
+    let remaining = cursor.get_ref().len() as u64 - offset.min(cursor.get_ref().len() as u64);
+    let available = remaining / tile_len;
+    let (size_x, size_y) = (map.size_x(), map.size_y());
+    let declared = size_x as u64 * size_y as u64;
+
+    if declared <= available {
+        let end = offset + declared * tile_len;
+        if end < offset + remaining {
+            fallbacks.push(Fallback::TrailingBytes { offset: end, len: offset + remaining - end });
+        }
+        return Ok(declared);
+    }
+    if size_x > 0 && available > 0 && available.is_multiple_of(size_x as u64) {
+        let used_y = (available / size_x as u64) as u32;
+        map.set_size_y(used_y);
+        fallbacks.push(Fallback::MapSize { declared: (size_x, size_y), used: (size_x, used_y) });
+        if !remaining.is_multiple_of(tile_len) {
+            let end = offset + available * tile_len;
+            fallbacks.push(Fallback::TrailingBytes { offset: end, len: remaining % tile_len });
+        }
+        return Ok(available);
+    }
+    if declared - available > MAX_PADDED_TILES {
+        return Err(Error::UnexpectedValue {
+            kind: FileKind::Rmm,
+            offset: size_offset,
+            expected: format!("a map size that fits {} tiles", available),
+            found: format!("{}x{}", size_x, size_y),
+        });
+    }
+    fallbacks.push(Fallback::TruncatedEntries {
+        declared: declared as u32,
+        read: available as u32,
+    });
+    Ok(available)
+}
+
+fn parse_v1(cursor: &mut Cursor<&[u8]>, lenient: bool) -> Result<MapTile, Error> {
     let b_0: u32 = cursor.read_u8()? as u32;
     let b_1: u32 = cursor.read_u8()? as u32;
     let b_2: u32 = cursor.read_u8()? as u32;
@@ -108,7 +234,14 @@ fn parse_v1(cursor: &mut Cursor<&[u8]>) -> Result<MapTile, Error> {
     let b_6: u32 = cursor.read_u8()? as u32;
     let b_7: u32 = cursor.read_u8()? as u32;
 
-    assert!(b_0 & 0x2 == 0);
+    if b_0 & 0x2 != 0 && !lenient {
+        return Err(Error::UnexpectedValue {
+            kind: FileKind::Rmm,
+            offset: cursor.position() - 8,
+            expected: "a tile with bit 1 of the first byte clear".into(),
+            found: format!("{:#04x}", b_0),
+        });
+    }
 
     let obj_file_num = (b_0 / 4) + (b_1 % 32) * 64;
     let tle_file_idx = ((b_2 % 128) * 8) + (b_1 / 32);
@@ -126,6 +259,8 @@ fn parse_v1(cursor: &mut Cursor<&[u8]>) -> Result<MapTile, Error> {
         tle_rmd_entry: Entry::new(tle_file_num, tle_file_idx),
         warp,
         collision,
+        unknown_1: (b_0 & 0x3) as u8,
+        unknown_2: b_5 as u8,
     };
 
     Ok(tile)
@@ -142,7 +277,14 @@ fn parse_v2(cursor: &mut Cursor<&[u8]>) -> Result<MapTile, Error> {
     let b_6: u32 = cursor.read_u8()? as u32;
     let b_7: u32 = cursor.read_u8()? as u32;
 
-    assert!(b_0 & 0x2 == 0);
+    if b_0 & 0x2 != 0 {
+        return Err(Error::UnexpectedValue {
+            kind: FileKind::Rmm,
+            offset: cursor.position() - 8,
+            expected: "a tile with bit 1 of the first byte clear".into(),
+            found: format!("{:#04x}", b_0),
+        });
+    }
 
     let obj_file_num = (b_0 >> 2) + ((b_1 & 0x1F) << 6);
     let tle_file_idx = (b_1 >> 5) + ((b_2 & 0x7F) << 3);
@@ -161,6 +303,8 @@ fn parse_v2(cursor: &mut Cursor<&[u8]>) -> Result<MapTile, Error> {
         tle_rmd_entry: Entry::new(tle_file_num, tle_file_idx),
         warp,
         collision,
+        unknown_1: (b_0 & 0x3) as u8,
+        unknown_2: b_5 as u8,
     };
 
     Ok(tile)
@@ -190,4 +334,157 @@ mod tests {
         let map = parse_rmm(data).unwrap();
         assert_eq!((map.size_x() * map.size_y()) as usize, map.tile_count());
     }
+
+    fn make_map_bytes(size_x: u32, size_y: u32, tiles: u32) -> Vec<u8> {
+        use crate::writer::rmm::write_rmm;
+        let mut map = Map::new();
+        map.set_size_x(size_x);
+        map.set_size_y(tiles / size_x);
+        for _ in 0..tiles {
+            map.add_tile(MapTile::new());
+        }
+        let mut data = write_rmm(&map).unwrap();
+        // the declared size, after the identifier and size_x
+        let size_y_offset = 1 + RMM_IDENTIFIER.len() + 4;
+        data[size_y_offset..size_y_offset + 4].copy_from_slice(&size_y.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_rmm_collision_and_warp() {
+        use crate::entity::collision::{Collision, SubSpot};
+        use crate::writer::rmm::write_rmm;
+
+        let mut map = Map::new();
+        map.set_size_x(5);
+        map.set_size_y(1);
+        for &(collision, warp, obj_idx) in [(0u32, 0u32, 4u32), (24, 16, 6), (49, 0, 7),
+                                             (72, 16, 2), (121, 3, 9)].iter() {
+            let mut tile = MapTile::new();
+            tile.obj_rmd_entry = Entry::new(1, obj_idx);
+            tile.collision = collision;
+            tile.warp = warp;
+            map.add_tile(tile);
+        }
+        let data = write_rmm(&map).unwrap();
+        let parsed = parse_rmm(&data).unwrap();
+        assert_eq!(parsed.tiles(), map.tiles());
+        assert_eq!(data, write_rmm(&parsed).unwrap());
+
+        let kinds: Vec<Collision> = parsed.tiles().iter().map(|t| t.collision_kind()).collect();
+        assert_eq!(kinds, vec![Collision::None, Collision::Full, Collision::LeftTop,
+                               Collision::RightBottom, Collision::Unknown(5)]);
+        let walkable = |spot| parsed.tiles().iter().map(|t| t.is_walkable(spot)).collect::<Vec<_>>();
+        assert_eq!(walkable(SubSpot::LeftTop), vec![true, false, false, true, false]);
+        assert_eq!(walkable(SubSpot::RightBottom), vec![true, false, true, false, false]);
+        let warps: Vec<bool> = parsed.tiles().iter().map(|t| t.is_warp()).collect();
+        assert_eq!(warps, vec![false, true, false, true, false]);
+        for kind in kinds {
+            assert_eq!(Collision::from_raw(kind.setting() * 24), kind);
+        }
+    }
+
+    #[test]
+    fn test_map_coordinates() {
+        use crate::entity::direction::Direction;
+
+        // a 4x3 map where each tile's object index is its position in the file
+        let mut map = Map::new();
+        map.set_size_x(4);
+        map.set_size_y(3);
+        for index in 0..12 {
+            let mut tile = MapTile::new();
+            tile.obj_rmd_entry = Entry::new(1, index);
+            map.add_tile(tile);
+        }
+        let idx = |tile: &MapTile| tile.obj_rmd_entry.index();
+
+        assert_eq!(map.tile_at(2, 1).map(idx), Some(6));
+        assert_eq!(map.tile_at(3, 2).map(idx), Some(11));
+        assert!(map.tile_at(4, 0).is_none());
+        assert!(map.tile_at(0, 3).is_none());
+        assert_eq!(map.tile_index(1, 2), Some(9));
+        assert_eq!(map.tile_coords(9), Some((1, 2)));
+        assert_eq!(map.tile_coords(12), None);
+
+        for ((x, y), tile) in map.enumerate_tiles() {
+            assert_eq!(map.tile_index(x, y), Some(idx(tile) as usize));
+        }
+        assert_eq!(map.enumerate_tiles().count(), 12);
+
+        let region: Vec<((u32, u32), u32)> = map.region(2, 1, 5, 5).map(|(c, t)| (c, idx(t))).collect();
+        assert_eq!(region, vec![((2, 1), 6), ((3, 1), 7), ((2, 2), 10), ((3, 2), 11)]);
+        assert_eq!(map.region(1, 1, 0, 2).count(), 0);
+        assert_eq!(map.region(9, 0, 2, 2).count(), 0);
+
+        assert_eq!(map.neighbour(1, 1, Direction::Up), Some((0, 0)));
+        assert_eq!(map.neighbour(1, 1, Direction::UpLeft), Some((0, 1)));
+        assert_eq!(map.neighbour(1, 1, Direction::Right), Some((2, 0)));
+        assert_eq!(map.neighbour(1, 1, Direction::DownRight), Some((2, 1)));
+        assert_eq!(map.neighbour(0, 0, Direction::Left), None);
+        assert_eq!(map.neighbour(3, 2, Direction::Down), None);
+        let corner: Vec<(Direction, u32)> = map.neighbours(0, 0).map(|(d, _, t)| (d, idx(t))).collect();
+        assert_eq!(corner, vec![(Direction::DownRight, 1), (Direction::Down, 5), (Direction::DownLeft, 4)]);
+        assert_eq!(map.neighbours(1, 1).count(), 8);
+        for &direction in Direction::ALL.iter() {
+            let (x, y) = map.neighbour(1, 1, direction).unwrap();
+            assert_eq!(map.neighbour(x, y, direction.opposite()), Some((1, 1)));
+        }
+    }
+
+    #[test]
+    fn test_directions_follow_projection() {
+        use crate::entity::direction::Direction;
+        use geometry::isometric::Projection;
+        use geometry::point::Point;
+
+        // a step in each direction moves the tile's centre that way on the screen
+        let projection = Projection::default();
+        let (w, h) = (projection.tile_width(), projection.tile_height());
+        let expected = [(0, -h), (w / 2, -h / 2), (w, 0), (w / 2, h / 2),
+                        (0, h), (-w / 2, h / 2), (-w, 0), (-w / 2, -h / 2)];
+        let from = Point::new(3, 5);
+        let center = projection.tile_center(&from);
+        for (direction, &(ex, ey)) in Direction::ALL.iter().zip(expected.iter()) {
+            let (dx, dy) = direction.offset();
+            let moved = projection.tile_center(&Point::new(from.x + dx, from.y + dy));
+            assert_eq!((moved.x - center.x, moved.y - center.y), (ex, ey), "{:?}", direction);
+        }
+    }
+
+    #[test]
+    fn test_rmm_lenient() {
+        let (map, fallbacks) = parse_rmm_lenient(&make_map_bytes(2, 2, 4)).unwrap();
+        assert_eq!(map.tile_count(), 4);
+        assert!(fallbacks.is_empty());
+
+        // a height that doesn't match the tiles is taken from the tiles
+        let (map, fallbacks) = parse_rmm_lenient(&make_map_bytes(2, 3, 4)).unwrap();
+        assert_eq!((map.size_x(), map.size_y(), map.tile_count()), (2, 2, 4));
+        assert_eq!(fallbacks, vec![Fallback::MapSize { declared: (2, 3), used: (2, 2) }]);
+
+        // no smaller height fits, so the missing tiles are padded
+        let mut data = make_map_bytes(3, 3, 6);
+        data.truncate(data.len() - 8);
+        let (map, fallbacks) = parse_rmm_lenient(&data).unwrap();
+        assert_eq!(map.tile_count(), 9);
+        assert_eq!(fallbacks, vec![Fallback::TruncatedEntries { declared: 9, read: 5 }]);
+
+        let mut data = make_map_bytes(2, 2, 4);
+        data[1] = b'r';
+        let tiles = data.len() - 4 * 8;
+        data[tiles] |= 0x2;
+        data.push(0);
+        match parse_rmm(&data) {
+            Err(Error::MissingIdentifier { kind: FileKind::Rmm, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let (map, fallbacks) = parse_rmm_lenient(&data).unwrap();
+        assert_eq!(map.tiles()[0].unknown_1, 0x2);
+        assert_eq!(fallbacks, vec![
+            Fallback::IgnoredIdentifier { found: "redMoon MapData 1.0".into() },
+            Fallback::TrailingBytes { offset: data.len() as u64 - 1, len: 1 },
+            Fallback::UnexpectedTileBits { count: 1 },
+        ]);
+    }
 }
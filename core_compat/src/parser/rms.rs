@@ -0,0 +1,225 @@
+//! The RMS sound files are read as a RIFF "WAVE" image with PCM samples,
+//! which may be preceded by a short header of its own that is kept as is.
+//!
+//! [RIFF]
+//! 4 bytes "RIFF"
+//! u32 riff size
+//! 4 bytes "WAVE"
+//!
+//! [CHUNK]
+//! 4 bytes chunk id ("fmt ", "data", ... others are skipped)
+//! u32 chunk size (chunks are padded to an even size)
+//!
+//! [fmt ]
+//! u16 audio format (1 = PCM)
+//! u16 channels
+//! u32 sample rate
+//! u32 byte rate
+//! u16 block align
+//! u16 bits per sample
+//!
+//! A `data` chunk whose size runs past the end of the file is cut off at the
+//! end of the file instead of being rejected.
+//!
+//! The layout above is an assumption that no sound file in this tree has been checked
+//! against yet: it is the plain wave layout, with a search for the "RIFF" tag in case the
+//! files start with a header like the other resource files do. The same goes for the
+//! `RLEs/Snd/snd{file}.rms` path that `sound_path` gives the `snd.lst` items.
+//! `test_rms_game_files` reads every sound of the game's `snd.lst` from the `data` folder,
+//! run it with `cargo test -- --ignored` where the game files are copied there.
+
+use std::io::Cursor;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+use byteorder::ReadBytesExt;
+use byteorder::LittleEndian as LE;
+
+use crate::error::Error;
+use crate::entity::entry::Entry;
+use crate::entity::file_kind::FileKind;
+use crate::entity::sound::{Sound, SampleFormat};
+
+/// How far into the file the RIFF image is searched for
+const MAX_HEADER_LEN: usize = 256;
+
+/// The path of the sound file that a `snd.lst` item points at; each file holds a single
+/// sound, so only the entry's file number is used
+pub fn sound_path(entry: &Entry) -> String {
+    format!("RLEs/Snd/snd{:05}.rms", entry.file())
+}
+
+pub fn parse_rms(data: &[u8]) -> Result<Sound, Error> {
+    let mut cursor = Cursor::new(data);
+    read_rms(&mut cursor).map_err(|err| err.in_file(FileKind::Rms, cursor.position()))
+}
+
+fn read_rms(cursor: &mut Cursor<&[u8]>) -> Result<Sound, Error> {
+    let data = *cursor.get_ref();
+    let riff_start = match find_riff(data) {
+        Some(riff_start) => riff_start,
+        None => return Err(Error::MissingIdentifier {
+            kind: FileKind::Rms,
+            offset: 0,
+            expected: "RIFF WAVE".into(),
+            found: String::from_utf8_lossy(&data[..data.len().min(12)]).into(),
+        }),
+    };
+    let mut sound = Sound::new();
+    sound.header = data[..riff_start].to_vec();
+
+    // skip "RIFF", the riff size and "WAVE"
+    cursor.seek(SeekFrom::Start(riff_start as u64 + 12))?;
+
+    let mut has_format = false;
+    let mut has_data = false;
+    while (cursor.position() as usize) + 8 <= data.len() {
+        let mut id = [0u8; 4];
+        for b in id.iter_mut() {
+            *b = cursor.read_u8()?;
+        }
+        let size = cursor.read_u32::<LE>()? as usize;
+        let start = cursor.position() as usize;
+        let end = start.saturating_add(size).min(data.len());
+
+        match &id {
+            b"fmt " => {
+                let audio_format = cursor.read_u16::<LE>()?;
+                sound.channels = cursor.read_u16::<LE>()?;
+                sound.sample_rate = cursor.read_u32::<LE>()?;
+                let _byte_rate = cursor.read_u32::<LE>()?;
+                let _block_align = cursor.read_u16::<LE>()?;
+                let bits_per_sample = cursor.read_u16::<LE>()?;
+                sound.format = match (audio_format, bits_per_sample) {
+                    (1, 8) => SampleFormat::U8,
+                    (1, 16) => SampleFormat::I16,
+                    _ => return Err(Error::UnexpectedValue {
+                        kind: FileKind::Rms,
+                        offset: start as u64,
+                        expected: "8 or 16 bit PCM samples".into(),
+                        found: format!("format {} with {} bit samples",
+                                       audio_format, bits_per_sample),
+                    }),
+                };
+                has_format = true;
+            }
+            b"data" => {
+                sound.data = data[start..end].to_vec();
+                has_data = true;
+            }
+            _ => (),
+        }
+
+        // chunks are word aligned
+        let next = end + (size & 0x1);
+        cursor.seek(SeekFrom::Start(next as u64))?;
+    }
+
+    if !has_format || !has_data {
+        return Err(Error::UnexpectedValue {
+            kind: FileKind::Rms,
+            offset: data.len() as u64,
+            expected: "a \"fmt \" and a \"data\" chunk".into(),
+            found: format!("{} chunk", if has_format { "no \"data\"" } else { "no \"fmt \"" }),
+        });
+    }
+    Ok(sound)
+}
+
+pub(crate) fn find_riff(data: &[u8]) -> Option<usize> {
+    let search_len = data.len().min(MAX_HEADER_LEN + 12);
+    (0..search_len.saturating_sub(11))
+        .find(|&idx| &data[idx..idx + 4] == b"RIFF" && &data[idx + 8..idx + 12] == b"WAVE")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_wave(channels: u16, rate: u32, bits: u16, samples: &[u8]) -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"WAVE");
+        // an unrelated chunk with an odd size
+        data.extend_from_slice(b"LIST");
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&[1, 2, 3, 0]);
+        data.extend_from_slice(b"fmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&channels.to_le_bytes());
+        data.extend_from_slice(&rate.to_le_bytes());
+        let block_align = channels * bits / 8;
+        data.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+        data.extend_from_slice(&block_align.to_le_bytes());
+        data.extend_from_slice(&bits.to_le_bytes());
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        data.extend_from_slice(samples);
+        data
+    }
+
+    #[test]
+    fn test_rms_plain_wave() {
+        let samples = [0x00, 0x01, 0xFF, 0x7F, 0x00, 0x80, 0x10, 0x00];
+        let sound = parse_rms(&make_wave(2, 22050, 16, &samples)).unwrap();
+        assert_eq!(sound.format, SampleFormat::I16);
+        assert_eq!(sound.sample_rate, 22050);
+        assert_eq!(sound.channels, 2);
+        assert_eq!(sound.frame_count(), 2);
+        assert!(sound.header.is_empty());
+        assert_eq!(sound.data, samples.to_vec());
+    }
+
+    #[test]
+    fn test_rms_with_header_and_short_data() {
+        let mut data = vec![0xAB; 20];
+        data.extend_from_slice(&make_wave(1, 11025, 8, &[0x80, 0x90, 0xA0, 0xB0]));
+        // claim more samples than the file holds
+        let len = data.len();
+        data.truncate(len - 1);
+        let sound = parse_rms(&data).unwrap();
+        assert_eq!(sound.header, vec![0xAB; 20]);
+        assert_eq!(sound.format, SampleFormat::U8);
+        assert_eq!(sound.data, vec![0x80, 0x90, 0xA0]);
+    }
+
+    #[test]
+    fn test_rms_errors() {
+        match parse_rms(b"not a sound file") {
+            Err(Error::MissingIdentifier { kind: FileKind::Rms, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let wave = make_wave(1, 8000, 12, &[0; 4]);
+        match parse_rms(&wave) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rms, offset: 32, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut no_data = make_wave(1, 8000, 8, &[]);
+        no_data.truncate(no_data.len() - 8);
+        match parse_rms(&no_data) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rms, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[ignore] // needs the game files in the data folder
+    fn test_rms_game_files() {
+        use std::path::Path;
+        use crate::parser::lst::parse_lst;
+        use crate::repository::vfs::Vfs;
+
+        let vfs = Vfs::new(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../data"));
+        let list = parse_lst(&vfs.read("RLEs/snd.lst").unwrap(), true).unwrap();
+        assert!(!list.items.is_empty());
+        for item in &list.items {
+            let path = sound_path(&item.entry);
+            let data = vfs.read(&path).unwrap_or_else(|err| panic!("item {}: {}", item.id, err));
+            let sound = parse_rms(&data).unwrap_or_else(|err| panic!("{}: {}", path, err));
+            assert!(sound.channels > 0 && sound.sample_rate > 0, "{}: {:?}", path, sound.format);
+            assert!(sound.frame_count() > 0, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! A cache of the parsed game files that follows the references between them.
+//!
+//! The map tiles (RMM) point at entries of the data files (RMD), whose images point at items
+//! of the list files (LST), which finally point at the sprites in the RLE files. The files are
+//! looked up through a `Vfs`, so their casing doesn't matter and overlays can replace them:
+//!
+//! DATAs/Map/Map00001.rmm
+//! DATAs/Tle/tle00001.rmd    (Obj/obj, Bul/bul, Ico/ico)
+//! RLEs/tle.lst              (obj, bul, ico)
+//! RLEs/Tle/tle00001.rle     (Obj/obj, Bul/bul, Ico/ico)
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::entity::entry::Entry;
+use crate::entity::file_kind::FileKind;
+use crate::entity::list::List;
+use crate::entity::map::Map;
+use crate::entity::map_sprites::MapSprites;
+use crate::entity::map_tile::MapTile;
+use crate::entity::pixel_format::PixelFormat;
+use crate::entity::resource::Resource;
+use crate::entity::rmd::Rmd;
+use crate::entity::rmd_type::RmdType;
+use crate::entity::sprite_type::SpriteType;
+use crate::entity::tile_sprite::TileSprite;
+use crate::parser::lst::parse_lst_lenient_with;
+use crate::parser::registry::VersionRegistry;
+use crate::parser::rle::RleReader;
+use crate::parser::rmd::parse_rmd_with;
+use crate::parser::rmm::parse_rmm_with;
+use crate::repository::vfs::Vfs;
+
+pub struct GameData {
+    vfs: Rc<Vfs>,
+    versions: VersionRegistry,
+    maps: HashMap<u32, Rc<Map>>,
+    rmds: HashMap<(RmdType, u32), Rc<Rmd>>,
+    lists: HashMap<RmdType, Rc<List>>,
+    rles: HashMap<(RmdType, u32), Rc<RleReader>>,
+}
+
+impl GameData {
+    /// `root` is the data directory holding the `DATAs` and `RLEs` folders
+    pub fn new(root: &Path) -> GameData {
+        GameData::with_vfs(Rc::new(Vfs::new(root)))
+    }
+
+    pub fn with_vfs(vfs: Rc<Vfs>) -> GameData {
+        GameData {
+            vfs,
+            versions: VersionRegistry::new(),
+            maps: HashMap::new(),
+            rmds: HashMap::new(),
+            lists: HashMap::new(),
+            rles: HashMap::new(),
+        }
+    }
+
+    /// Reads the files with the versions of another client release than 3.9
+    pub fn with_versions(mut self, versions: VersionRegistry) -> GameData {
+        self.versions = versions;
+        self
+    }
+
+    pub fn vfs(&self) -> &Rc<Vfs> {
+        &self.vfs
+    }
+
+    pub fn versions(&self) -> &VersionRegistry {
+        &self.versions
+    }
+
+    pub fn map(&mut self, number: u32) -> Result<Rc<Map>, Error> {
+        if let Some(map) = self.maps.get(&number) {
+            return Ok(map.clone());
+        }
+        let path = format!("DATAs/Map/Map{:05}.rmm", number);
+        let map = Rc::new(parse_rmm_with(&self.vfs.read(&path)?, &self.versions)?);
+        self.maps.insert(number, map.clone());
+        Ok(map)
+    }
+
+    pub fn rmd(&mut self, kind: RmdType, number: u32) -> Result<Rc<Rmd>, Error> {
+        if let Some(rmd) = self.rmds.get(&(kind, number)) {
+            return Ok(rmd.clone());
+        }
+        let (folder, prefix) = names(kind)?;
+        let path = format!("DATAs/{}/{}{:05}.rmd", folder, prefix, number);
+        let rmd = Rc::new(parse_rmd_with(kind, &self.vfs.read(&path)?, &self.versions)?);
+        self.rmds.insert((kind, number), rmd.clone());
+        Ok(rmd)
+    }
+
+    /// The list of all of the sprites of `kind`; some of the lists advertise the wrong version,
+    /// so these are read leniently.
+    pub fn list(&mut self, kind: RmdType) -> Result<Rc<List>, Error> {
+        if let Some(list) = self.lists.get(&kind) {
+            return Ok(list.clone());
+        }
+        let (_, prefix) = names(kind)?;
+        let path = format!("RLEs/{}.lst", prefix);
+        let (list, _) = parse_lst_lenient_with(&self.vfs.read(&path)?, &self.versions)?;
+        let list = Rc::new(list);
+        self.lists.insert(kind, list.clone());
+        Ok(list)
+    }
+
+    /// The reader of an RLE file, the resources themselves are decoded by `resource`
+    pub fn rle(&mut self, kind: RmdType, number: u32) -> Result<Rc<RleReader>, Error> {
+        if let Some(reader) = self.rles.get(&(kind, number)) {
+            return Ok(reader.clone());
+        }
+        let (folder, prefix) = names(kind)?;
+        let path = format!("RLEs/{}/{}{:05}.rle", folder, prefix, number);
+        let mut reader = RleReader::new(number, self.vfs.read(&path)?)?;
+        reader.set_color_key(sprite_type(kind).color_key());
+        let reader = Rc::new(reader);
+        self.rles.insert((kind, number), reader.clone());
+        Ok(reader)
+    }
+
+    /// Every sprite drawn for `tile`: first the floor tile, then the object from the back to
+    /// the front. The RLE files are checked to hold the resources but nothing is decoded.
+    /// The floor and the object are resolved on their own, a broken object still leaves the
+    /// floor; the errors of the parts that didn't resolve are returned next to the sprites.
+    pub fn resolve_tile(&mut self, tile: &MapTile) -> (Vec<TileSprite>, Vec<Error>) {
+        let mut sprites = Vec::<TileSprite>::new();
+        let mut errors = Vec::<Error>::new();
+        let parts = [(RmdType::Tile, tile.tle_rmd_entry), (RmdType::Object, tile.obj_rmd_entry)];
+        for &(kind, rmd_entry) in parts.iter() {
+            match self.resolve_entry(kind, rmd_entry) {
+                Ok(mut part) => sprites.append(&mut part),
+                Err(err) => errors.push(err),
+            }
+        }
+        (sprites, errors)
+    }
+
+    /// Resolves every tile of `map` once, see `resolve_tile`
+    pub fn resolve_map(&mut self, map: &Map) -> MapSprites {
+        let mut map_sprites = MapSprites::new(map.size_x());
+        for ((x, y), tile) in map.enumerate_tiles() {
+            let (sprites, errors) = self.resolve_tile(tile);
+            map_sprites.add_tile(x, y, sprites, errors);
+        }
+        map_sprites
+    }
+
+    /// Decodes the RLE resource of a resolved sprite
+    pub fn resource(&mut self, sprite: &TileSprite) -> Result<Resource, Error> {
+        self.resource_as(sprite, PixelFormat::Rgba8888)
+    }
+
+    /// Same as `resource`, see `parse_rle_as` for the `pixel_format`
+    pub fn resource_as(
+        &mut self,
+        sprite: &TileSprite,
+        pixel_format: PixelFormat
+    ) -> Result<Resource, Error> {
+        // `resolve_tile` only hands out floor tiles and objects
+        let kind = match sprite.sprite_type {
+            SpriteType::Tile => RmdType::Tile,
+            _ => RmdType::Object,
+        };
+        let entry = sprite.rle_entry;
+        match self.rle(kind, entry.file())?.read_resource_as(entry.index(), pixel_format)? {
+            Some(resource) => Ok(resource),
+            None => Err(missing(FileKind::Rle, entry.file(), entry.index())),
+        }
+    }
+
+    /// The sprites of one RMD entry, all of them or none
+    fn resolve_entry(&mut self, kind: RmdType, rmd_entry: Entry) -> Result<Vec<TileSprite>, Error> {
+        let mut sprites = Vec::<TileSprite>::new();
+        // file 0 is used for tiles without an object or floor
+        if rmd_entry.file() == 0 {
+            return Ok(sprites);
+        }
+        let sprite_type = sprite_type(kind);
+        let rmd = self.rmd(kind, rmd_entry.file())?;
+        let list = self.list(kind)?;
+        let entry = match rmd.get_entry(rmd_entry.index() as usize) {
+            Some(entry) => entry,
+            None => return Err(missing(FileKind::Rmd, rmd_entry.file(), rmd_entry.index())),
+        };
+        for img in entry.images_by_layer() {
+            // negative ids are empty image slots
+            for &id in img.image_id.iter().filter(|&&id| id >= 0) {
+                let item = match list.get_item(id as usize) {
+                    Some(item) => item,
+                    None => return Err(missing(FileKind::Lst, 0, id as u32)),
+                };
+                let rle_entry = item.entry;
+                if !self.rle(kind, rle_entry.file())?.has_resource(rle_entry.index()) {
+                    return Err(missing(FileKind::Rle, rle_entry.file(), rle_entry.index()));
+                }
+                sprites.push(TileSprite {
+                    sprite_type,
+                    rmd_entry,
+                    list_id: id as u32,
+                    rle_entry,
+                    source_x1: img.source_x1,
+                    source_y1: img.source_y1,
+                    source_x2: img.source_x2,
+                    source_y2: img.source_y2,
+                    dest_x: img.dest_x,
+                    dest_y: img.dest_y,
+                    layer: img.layer(),
+                    draw_type: img.draw_kind(),
+                });
+            }
+        }
+        Ok(sprites)
+    }
+}
+
+/// The folder and file name prefix of the files of `kind`
+fn names(kind: RmdType) -> Result<(&'static str, &'static str), Error> {
+    match kind {
+        RmdType::Tile => Ok(("Tle", "tle")),
+        RmdType::Object => Ok(("Obj", "obj")),
+        RmdType::Bullet => Ok(("Bul", "bul")),
+        RmdType::Icon => Ok(("Ico", "ico")),
+        // every character class has its own list and folder
+        RmdType::Character => Err(Error::UnsupportedData(kind)),
+    }
+}
+
+/// The kind of sprite in the RLE files of `kind`
+fn sprite_type(kind: RmdType) -> SpriteType {
+    match kind {
+        RmdType::Bullet => SpriteType::Bullet,
+        RmdType::Character => SpriteType::Character,
+        RmdType::Icon => SpriteType::Icon,
+        RmdType::Object => SpriteType::Object,
+        RmdType::Tile => SpriteType::Tile,
+    }
+}
+
+fn missing(kind: FileKind, file: u32, index: u32) -> Error {
+    Error::MissingReference { kind, file, index }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::entity::draw_type::DrawType;
+    use crate::entity::list_version::ListVersion;
+    use crate::entity::render_layer::RenderLayer;
+    use crate::entity::rmd_builder::RmdBuilder;
+    use crate::entity::rmd_image::RmdImage;
+    use crate::entity::resource_file::ResourceFile;
+    use crate::writer::lst::write_lst;
+    use crate::writer::rle::write_rle;
+    use crate::writer::rmd::write_rmd;
+    use crate::writer::rmm::write_rmm;
+
+    fn write_file(root: &Path, path: &str, data: &[u8]) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn make_rle(file_number: u32, count: u32) -> Vec<u8> {
+        let mut rf = ResourceFile::new();
+        rf.file_number = file_number;
+        for index in 0..count {
+            let mut resource = Resource::new();
+            resource.set_index(index);
+            resource.width = 1 + index as i32;
+            resource.height = 1;
+            resource.image_raw = vec![0xFF; 4 * (1 + index as usize)];
+            rf.resources.push(resource);
+        }
+        write_rle(&rf).unwrap()
+    }
+
+    /// A data directory with one 2x1 map; tile (0, 0) has a floor and a two part object,
+    /// tile (1, 0) only points at an object entry that doesn't exist.
+    fn make_data_dir(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("core_compat_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut map = Map::new();
+        map.set_map_number(1);
+        map.set_size_x(2);
+        map.set_size_y(1);
+        let mut tile = MapTile::new();
+        tile.tle_rmd_entry = Entry::new(1, 0);
+        tile.obj_rmd_entry = Entry::new(2, 1);
+        tile.collision = 1; // odd object entries need a collision remainder
+        map.add_tile(tile);
+        let mut tile = MapTile::new();
+        tile.obj_rmd_entry = Entry::new(2, 5);
+        tile.collision = 1;
+        map.add_tile(tile);
+        write_file(&root, "DATAs/Map/Map00001.rmm", &write_rmm(&map).unwrap());
+
+        let tle = RmdBuilder::new(RmdType::Tile)
+            .entry(vec![RmdImage::new().with_source(0, 0, 48, 24).with_image_ids(&[0])])
+            .build();
+        write_file(&root, "DATAs/Tle/tle00001.rmd", &write_rmd(&tle).unwrap());
+        let obj = RmdBuilder::new(RmdType::Object)
+            .entry(vec![])
+            .entry(vec![
+                RmdImage::new().with_render_z(2).with_draw_type(2).with_dest(3, -4).with_image_ids(&[1, -1]),
+                RmdImage::new().with_render_z(0).with_draw_type(0).with_image_ids(&[0]),
+            ])
+            .build();
+        write_file(&root, "DATAs/Obj/obj00002.rmd", &write_rmd(&obj).unwrap());
+
+        let mut tle_list = List::new();
+        tle_list.add_item("floor".into(), Entry::new(0, 0));
+        write_file(&root, "RLEs/tle.lst", &write_lst(&tle_list, ListVersion::V1_0).unwrap());
+        let mut obj_list = List::new();
+        obj_list.add_item("shadow".into(), Entry::new(3, 0));
+        obj_list.add_item("house".into(), Entry::new(3, 1));
+        write_file(&root, "RLEs/obj.lst", &write_lst(&obj_list, ListVersion::V1_0).unwrap());
+
+        write_file(&root, "RLEs/Tle/tle00000.rle", &make_rle(0, 1));
+        write_file(&root, "RLEs/Obj/obj00003.rle", &make_rle(3, 2));
+        root
+    }
+
+    #[test]
+    fn test_game_data_resolves_map_tiles() {
+        let root = make_data_dir("resolve");
+        let mut data = GameData::new(&root);
+        let map = data.map(1).unwrap();
+        assert!(Rc::ptr_eq(&map, &data.map(1).unwrap()));
+
+        let (sprites, errors) = data.resolve_tile(map.tile_at(0, 0).unwrap());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let summary: Vec<(SpriteType, u32, Entry)> = sprites.iter()
+            .map(|s| (s.sprite_type, s.list_id, s.rle_entry))
+            .collect();
+        assert_eq!(summary, vec![
+            (SpriteType::Tile, 0, Entry::new(0, 0)),
+            (SpriteType::Object, 0, Entry::new(3, 0)),
+            (SpriteType::Object, 1, Entry::new(3, 1)),
+        ]);
+        assert_eq!(sprites[0].source_x2, 48);
+        assert_eq!(sprites[1].layer, RenderLayer::Back);
+        assert_eq!(sprites[1].draw_type, DrawType::Shadow);
+        assert_eq!((sprites[2].dest_x, sprites[2].dest_y), (3, -4));
+        assert_eq!(sprites[2].rmd_entry, Entry::new(2, 1));
+
+        let house = data.resource(&sprites[2]).unwrap();
+        assert_eq!((house.width, house.file_num), (2, Some(3)));
+        let floor = data.resource_as(&sprites[0], PixelFormat::R5g6b5).unwrap();
+        assert_eq!((floor.pixel_format, floor.image_raw.len()), (PixelFormat::R5g6b5, 2));
+
+        match data.resolve_tile(map.tile_at(1, 0).unwrap()) {
+            (ref sprites, ref errors) if sprites.is_empty() => match errors[..] {
+                [Error::MissingReference { kind: FileKind::Rmd, file: 2, index: 5 }] => (),
+                ref other => panic!("unexpected errors: {:?}", other),
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let map_sprites = data.resolve_map(&map);
+        assert_eq!(map_sprites.tile_at(0, 0), &sprites[..]);
+        assert!(map_sprites.tile_at(1, 0).is_empty());
+        assert!(map_sprites.tile_at(2, 0).is_empty());
+        match map_sprites.errors() {
+            [((1, 0), Error::MissingReference { kind: FileKind::Rmd, file: 2, index: 5 })] => (),
+            other => panic!("unexpected errors: {:?}", other),
+        }
+        match data.map(2) {
+            Err(Error::Io(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_game_data_reports_broken_chains() {
+        let root = make_data_dir("broken");
+        // the object list loses the house and the shadow's RLE file loses its resources
+        let mut obj_list = List::new();
+        obj_list.add_item("shadow".into(), Entry::new(3, 0));
+        write_file(&root, "RLEs/obj.lst", &write_lst(&obj_list, ListVersion::V1_0).unwrap());
+        let mut data = GameData::new(&root);
+        let map = data.map(1).unwrap();
+        // the floor is still drawn
+        let (sprites, errors) = data.resolve_tile(&map.tiles()[0]);
+        assert_eq!(sprites.len(), 1);
+        assert_eq!(sprites[0].sprite_type, SpriteType::Tile);
+        match errors[..] {
+            [Error::MissingReference { kind: FileKind::Lst, file: 0, index: 1 }] => (),
+            ref other => panic!("unexpected errors: {:?}", other),
+        }
+
+        write_file(&root, "RLEs/Obj/obj00003.rle", &make_rle(3, 0));
+        let mut data = GameData::new(&root);
+        match data.resolve_tile(&map.tiles()[0]).1[..] {
+            [Error::MissingReference { kind: FileKind::Rle, file: 3, index: 0 }] => (),
+            ref other => panic!("unexpected errors: {:?}", other),
+        }
+        match data.rmd(RmdType::Character, 1) {
+            Err(Error::UnsupportedData(RmdType::Character)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod game_data;
+pub mod vfs;
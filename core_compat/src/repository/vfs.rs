@@ -0,0 +1,169 @@
+//! A read only view over the game's data directory and any number of overlay directories.
+//!
+//! The paths are given the way the game names them, e.g. `DATAs/Map/Map00001.rmm`, with `/`
+//! or `\` separators. Each part of the path is matched case-insensitively, since the original
+//! data files come with mixed casing, and the overlays added last are searched first so a
+//! mod folder only has to hold the files it replaces.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::error::Error;
+
+pub struct Vfs {
+    roots: Vec<PathBuf>,
+    // the lowercase names of the entries of every directory looked into
+    listings: RefCell<HashMap<PathBuf, Rc<HashMap<String, OsString>>>>,
+}
+
+impl Vfs {
+    pub fn new(base: &Path) -> Vfs {
+        Vfs {
+            roots: vec![base.into()],
+            listings: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Adds a directory whose files take precedence over the ones added before it
+    pub fn with_overlay(mut self, overlay: &Path) -> Vfs {
+        self.add_overlay(overlay);
+        self
+    }
+
+    pub fn add_overlay(&mut self, overlay: &Path) {
+        self.roots.push(overlay.into());
+    }
+
+    /// The base directory followed by the overlays, in the order they were added
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// The real path of the file or directory at `path`, from the last overlay that has it
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        self.roots.iter().rev().filter_map(|root| self.resolve_in(root, path)).next()
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        match self.resolve(path) {
+            Some(real_path) => Ok(fs::read(real_path)?),
+            None => Err(Error::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found in {:?}", path, self.roots)))),
+        }
+    }
+
+    /// The real paths of the files in the directory at `path` over all of the roots, sorted by
+    /// name. Names that only differ in case are the same file, the last overlay's one is kept.
+    pub fn list(&self, path: &str) -> Vec<PathBuf> {
+        let mut files = HashMap::<String, PathBuf>::new();
+        for root in self.roots.iter() {
+            let dir = match self.resolve_in(root, path) {
+                Some(dir) => dir,
+                None => continue,
+            };
+            for (lower, name) in self.listing(&dir).iter() {
+                let file = dir.join(name);
+                if file.is_file() {
+                    files.insert(lower.clone(), file);
+                }
+            }
+        }
+        let mut files: Vec<(String, PathBuf)> = files.into_iter().collect();
+        files.sort();
+        files.into_iter().map(|(_, file)| file).collect()
+    }
+
+    fn resolve_in(&self, root: &Path, path: &str) -> Option<PathBuf> {
+        let mut current = root.to_path_buf();
+        for part in path.split(['/', '\\']).filter(|part| !part.is_empty()) {
+            let exact = current.join(part);
+            if exact.exists() {
+                current = exact;
+                continue;
+            }
+            let name = self.listing(&current).get(&part.to_lowercase())?.clone();
+            current.push(name);
+        }
+        if current.exists() {
+            Some(current)
+        } else {
+            None
+        }
+    }
+
+    /// The entries of `dir` by their lowercase name; empty if it can't be read
+    fn listing(&self, dir: &Path) -> Rc<HashMap<String, OsString>> {
+        if let Some(listing) = self.listings.borrow().get(dir) {
+            return listing.clone();
+        }
+        let mut listing = HashMap::<String, OsString>::new();
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name();
+                let lower = name.to_string_lossy().to_lowercase();
+                listing.entry(lower).or_insert(name);
+            }
+        }
+        let listing = Rc::new(listing);
+        self.listings.borrow_mut().insert(dir.to_path_buf(), listing.clone());
+        listing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(root: &Path, path: &str, data: &[u8]) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_vfs_case_insensitive_overlay() {
+        let tmp = std::env::temp_dir().join(format!("core_compat_vfs_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let base = tmp.join("data");
+        let overlay = tmp.join("mod");
+        write_file(&base, "DATAs/Map/MAP00001.RMM", b"base map 1");
+        write_file(&base, "DATAs/Map/map00002.rmm", b"base map 2");
+        write_file(&base, "rles/OBJ.LST", b"base list");
+        write_file(&overlay, "datas/MAP/Map00002.rmm", b"mod map 2");
+        write_file(&overlay, "datas/MAP/Map00003.rmm", b"mod map 3");
+
+        let vfs = Vfs::new(&base).with_overlay(&overlay);
+        assert_eq!(vfs.read("DATAs/Map/Map00001.rmm").unwrap(), b"base map 1");
+        assert_eq!(vfs.read("DATAs\\Map\\Map00002.rmm").unwrap(), b"mod map 2");
+        assert_eq!(vfs.read("DATAs/Map/Map00003.rmm").unwrap(), b"mod map 3");
+        assert_eq!(vfs.read("RLEs/obj.lst").unwrap(), b"base list");
+        assert!(vfs.exists("datas/map"));
+        assert!(!vfs.exists("DATAs/Map/Map00004.rmm"));
+        match vfs.read("DATAs/Map/Map00004.rmm") {
+            Err(Error::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let maps: Vec<Vec<u8>> = vfs.list("DATAs/Map").iter()
+            .map(|path| fs::read(path).unwrap())
+            .collect();
+        assert_eq!(maps, vec![b"base map 1".to_vec(), b"mod map 2".to_vec(), b"mod map 3".to_vec()]);
+        assert!(vfs.list("DATAs/Chr").is_empty());
+
+        // without the overlay the base files are used
+        let vfs = Vfs::new(&base);
+        assert_eq!(vfs.read("DATAs/Map/Map00002.rmm").unwrap(), b"base map 2");
+        assert_eq!(vfs.list("DATAs/Map").len(), 2);
+        let _ = fs::remove_dir_all(&tmp);
+    }
+}
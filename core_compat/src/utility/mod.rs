@@ -1,2 +1,3 @@
 pub mod pixel;
 pub mod parsing;
+pub mod writing;
@@ -3,33 +3,13 @@ use std::io::Cursor;
 use byteorder::ReadBytesExt;
 use byteorder::LittleEndian as LE;
 
-use cp949::cp949_to_utf8;
-
+use crate::entity::text::Text;
 use crate::error::Error;
 
-pub fn parse_string(cursor: &mut Cursor<&[u8]>) -> Result<String, Error> {
-    let string_length = cursor.read_u8()?;
-    let mut str_vec = Vec::<u8>::new();
-    for _ in 0..string_length {
-        let chr = cursor.read_u8()?;
-        str_vec.push(chr);
-    }
-    let string = String::from_utf8(str_vec)?;
-    Ok(string)
-}
-
-pub fn parse_cp949(cursor: &mut Cursor<&[u8]>) -> Result<String, Error> {
-    let string_length = cursor.read_u8()?;
-    // println!("string_length: {:?}", string_length);
-    let mut str_vec = Vec::<u8>::new();
-    for _ in 0..string_length {
-        let chr = cursor.read_u8()?;
-        if chr != 0 {
-            str_vec.push(chr);
-        }
-    }
-    let string = cp949_to_utf8(&str_vec);
-    Ok(string)
+/// Reads a length prefixed string of a game file. All of the text of the game files goes
+/// through here: it is cp949, of which ascii is a part, and `Text` keeps the raw bytes too.
+pub fn parse_text(cursor: &mut Cursor<&[u8]>) -> Result<Text, Error> {
+    Ok(Text::from_raw(parse_u8_vec(cursor)?))
 }
 
 pub fn parse_u8_vec(cursor: &mut Cursor<&[u8]>) -> Result<Vec<u8>, Error> {
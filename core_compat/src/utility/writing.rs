@@ -0,0 +1,17 @@
+use crate::entity::text::Text;
+use crate::error::Error;
+
+/// Writes a length prefixed byte string, the counterpart of `parse_u8_vec`
+pub fn write_u8_vec(out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), Error> {
+    if bytes.len() > 0xFF {
+        return Err(Error::StringTooLong(bytes.len()));
+    }
+    out.push(bytes.len() as u8);
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Writes the raw bytes of a string, the counterpart of `parse_text`
+pub fn write_text(out: &mut Vec<u8>, text: &Text) -> Result<(), Error> {
+    write_u8_vec(out, text.raw())
+}
@@ -0,0 +1,128 @@
+//! Serializes a `List` into either of the "RedMoon Lst File" layouts read by
+//! `parser::lst`.
+//!
+//! [HEADER]
+//! String "RedMoon Lst File"
+//! String version ("1.0" or "1.2")
+//! u32 next free id
+//! u32 item count
+//!
+//! [ITEM]
+//! String name
+//! u32 id
+//! u32 file number
+//! u32 file index
+//! u32 unknown_2 (1.2 only)
+
+use byteorder::WriteBytesExt;
+use byteorder::LittleEndian as LE;
+
+use crate::error::Error;
+use crate::entity::list::List;
+use crate::entity::list_version::ListVersion;
+use crate::utility::writing::{write_u8_vec, write_text};
+
+const LST_IDENTIFIER: &str = "RedMoon Lst File";
+
+pub fn write_lst(list: &List, version: ListVersion) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::<u8>::new();
+
+    write_u8_vec(&mut data, LST_IDENTIFIER.as_bytes())?;
+    let version_str = match version {
+        ListVersion::V1_0 => "1.0",
+        ListVersion::V1_2 => "1.2",
+    };
+    write_u8_vec(&mut data, version_str.as_bytes())?;
+
+    data.write_u32::<LE>(list.next_free_id)?;
+    data.write_u32::<LE>(list.items.len() as u32)?;
+    for item in list.items.iter() {
+        write_text(&mut data, &item.name)?;
+        data.write_u32::<LE>(item.id)?;
+        data.write_u32::<LE>(item.entry.file())?;
+        data.write_u32::<LE>(item.entry.index())?;
+        if version == ListVersion::V1_2 {
+            data.write_u32::<LE>(item.unknown_2)?;
+        }
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::entry::Entry;
+    use crate::parser::lst::parse_lst;
+
+    fn make_list() -> List {
+        let mut list = List::new();
+        list.next_free_id = 10;
+        list.add_item("tree".into(), Entry::new(1, 0));
+        list.add_item("rock".into(), Entry::new(1, 1));
+        list.add_item("".into(), Entry::new(7, 42));
+        list
+    }
+
+    #[test]
+    fn test_write_lst_1_0_round_trip() {
+        let list = make_list();
+        let data = write_lst(&list, ListVersion::V1_0).unwrap();
+        let parsed = parse_lst(&data, false).unwrap();
+        assert_eq!(parsed.version, ListVersion::V1_0);
+        assert_eq!(parsed.next_free_id, 13);
+        assert_eq!(parsed.items, list.items);
+        assert_eq!(data, write_lst(&parsed, parsed.version).unwrap());
+    }
+
+    #[test]
+    fn test_write_lst_1_2_round_trip() {
+        let mut list = make_list();
+        list.items[1].unknown_2 = 0xCAFE;
+        let data = write_lst(&list, ListVersion::V1_2).unwrap();
+        let parsed = parse_lst(&data, false).unwrap();
+        assert_eq!(parsed.version, ListVersion::V1_2);
+        assert_eq!(parsed.items, list.items);
+        assert_eq!(data, write_lst(&parsed, parsed.version).unwrap());
+    }
+
+    #[test]
+    fn test_write_lst_drops_unknown_2_for_1_0() {
+        let mut list = make_list();
+        list.items[0].unknown_2 = 5;
+        let data = write_lst(&list, ListVersion::V1_0).unwrap();
+        let parsed = parse_lst(&data, false).unwrap();
+        assert_eq!(parsed.items[0].unknown_2, 0);
+        assert_eq!(parsed.items.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_lst_unknown_version() {
+        use crate::entity::file_kind::FileKind;
+
+        let mut data = write_lst(&make_list(), ListVersion::V1_0).unwrap();
+        data[20] = b'9';
+        match parse_lst(&data, false) {
+            Err(Error::UnexpectedValue { kind: FileKind::Lst, offset: 17, ref found, .. }) => {
+                assert_eq!(found, "\"1.9\"");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|l| l.items.len())),
+        }
+        // the forced 1.2 layout doesn't look at the version
+        assert!(parse_lst(&data, true).is_err());
+    }
+
+    #[test]
+    fn test_allocate_id() {
+        let mut list = make_list();
+        assert_eq!(list.items[0].id, 10);
+        assert_eq!(list.items[2].id, 12);
+        assert_eq!(list.allocate_id(), 13);
+        // a stale next_free_id doesn't hand out a used ID again
+        list.next_free_id = 0;
+        let id = list.add_item("new".into(), Entry::new(2, 0));
+        assert_eq!(id, 13);
+        assert_eq!(list.next_free_id, 14);
+        assert_eq!(list.get_item(13).unwrap().name, "new");
+    }
+}
@@ -0,0 +1,4 @@
+pub mod lst;
+pub mod rle;
+pub mod rmd;
+pub mod rmm;
@@ -0,0 +1,315 @@
+//! This module has the methods for encoding `ResourceFile`s back into the
+//! Redmoon Online RLE format read by `parser::rle`.
+//!
+//! [HEADER]
+//! 14 bytes "Resource File\0"
+//! u32 next free offset (end of the file)
+//! u32 total resources
+//! [u32; total resources] resource offsets (0 for missing indices)
+//!
+//! [RESOURCE]
+//! u32 length of the resource after this field
+//! i32 offset x, i32 offset y, i32 width, i32 height
+//! u32 unknown_1 .. unknown_4
+//! opcode stream:
+//!   0x01 u32 n, [u16; n] -- paint `n` r5g6b5 pixels
+//!   0x02 i32 n           -- move the column by `n` bytes (two per pixel)
+//!   0x03                 -- next line
+//!   0x00                 -- end of resource
+//!
+//! NOTE: The decoder does not reset the column on a new line, so every skip
+//!       is written relative to where the previous paint run ended.
+
+use byteorder::WriteBytesExt;
+use byteorder::LittleEndian as LE;
+
+use crate::error::Error;
+use crate::entity::color_key::ColorKey;
+use crate::entity::resource::Resource;
+use crate::entity::pixel_format::PixelFormat;
+use crate::entity::resource_file::ResourceFile;
+
+const RLE_IDENTIFIER: &[u8; 14] = b"Resource File\0";
+const FILE_HEADER_LEN: u32 = 14 + 4 + 4;
+
+pub fn write_rle(resource_file: &ResourceFile) -> Result<Vec<u8>, Error> {
+    write_rle_with(resource_file, ColorKey::Off)
+}
+
+/// Same as `write_rle` but painted pixels of the `color_key` colour are written as transparent,
+/// so the file decodes the same with or without the key.
+pub fn write_rle_with(resource_file: &ResourceFile, color_key: ColorKey) -> Result<Vec<u8>, Error> {
+    // lay the resources out in index order, leaving null offsets for the gaps
+    let total_resources = resource_file.resources.iter()
+        .map(|res| res.index() + 1)
+        .max()
+        .unwrap_or(0);
+    let mut slots: Vec<Option<&Resource>> = vec![None; total_resources as usize];
+    for resource in resource_file.resources.iter() {
+        let slot = &mut slots[resource.index() as usize];
+        if slot.is_some() {
+            return Err(Error::DuplicateResourceIndex(resource.index()));
+        }
+        *slot = Some(resource);
+    }
+
+    // encode the resources first so the offset table can be filled in
+    let mut offsets = Vec::<u32>::new();
+    let mut body = Vec::<u8>::new();
+    let body_start = FILE_HEADER_LEN + total_resources * 4;
+    for slot in slots.iter() {
+        match *slot {
+            Some(resource) => {
+                offsets.push(body_start + body.len() as u32);
+                write_resource(resource, color_key, &mut body)?;
+            }
+            None => offsets.push(0),
+        }
+    }
+
+    let mut data = Vec::<u8>::new();
+    data.extend_from_slice(RLE_IDENTIFIER);
+    data.write_u32::<LE>(body_start + body.len() as u32)?;
+    data.write_u32::<LE>(total_resources)?;
+    for offset in offsets {
+        data.write_u32::<LE>(offset)?;
+    }
+    data.extend_from_slice(&body);
+    Ok(data)
+}
+
+fn write_resource(resource: &Resource, color_key: ColorKey, out: &mut Vec<u8>) -> Result<(), Error> {
+    let width = resource.width;
+    let height = resource.height;
+    let total_px = (width.max(0) * height.max(0)) as usize;
+    let has_mask = resource.pixel_format != PixelFormat::R5g6b5 || resource.mask.len() == total_px;
+    if width <= 0 || height <= 0 || !has_mask
+        || resource.image_raw.len() != total_px * resource.pixel_format.bytes_per_pixel() {
+        return Err(Error::InvalidImageSize(resource.index()));
+    }
+
+    let mut block = Vec::<u8>::new();
+    block.write_i32::<LE>(resource.offset_x)?;
+    block.write_i32::<LE>(resource.offset_y)?;
+    block.write_i32::<LE>(width)?;
+    block.write_i32::<LE>(height)?;
+    block.write_u32::<LE>(resource.unknown_1)?;
+    block.write_u32::<LE>(resource.unknown_2)?;
+    block.write_u32::<LE>(resource.unknown_3)?;
+    block.write_u32::<LE>(resource.unknown_4)?;
+
+    let pixels = &resource.image_raw;
+    let colour = |idx: usize| match resource.pixel_format {
+        PixelFormat::Rgba8888 => {
+            let (r, g, b) = (pixels[idx * 4], pixels[idx * 4 + 1], pixels[idx * 4 + 2]);
+            format_rgb_r5g6b5(r, g, b)
+        }
+        PixelFormat::R5g6b5 => pixels[idx * 2] as u16 | (pixels[idx * 2 + 1] as u16) << 8,
+    };
+    let is_painted = |x: i32, y: i32| {
+        let idx = (y * width + x) as usize;
+        let covered = match resource.pixel_format {
+            PixelFormat::Rgba8888 => pixels[idx * 4 + 3] != 0,
+            PixelFormat::R5g6b5 => resource.mask[idx] != 0,
+        };
+        covered && !color_key.is_keyed(colour(idx))
+    };
+
+    // the decoder's column, which carries over between lines
+    let mut cursor_x = 0i32;
+    for y in 0..height {
+        if y > 0 {
+            block.push(0x03);
+        }
+        let mut x = 0i32;
+        while x < width {
+            if !is_painted(x, y) {
+                x += 1;
+                continue;
+            }
+            let run_start = x;
+            while x < width && is_painted(x, y) {
+                x += 1;
+            }
+            if run_start != cursor_x {
+                block.push(0x02);
+                block.write_i32::<LE>((run_start - cursor_x) * 2)?;
+            }
+            block.push(0x01);
+            block.write_u32::<LE>((x - run_start) as u32)?;
+            for px in run_start..x {
+                block.write_u16::<LE>(colour((y * width + px) as usize))?;
+            }
+            cursor_x = x;
+        }
+    }
+    block.push(0x00);
+
+    out.write_u32::<LE>(block.len() as u32)?;
+    out.extend_from_slice(&block);
+    Ok(())
+}
+
+/// Inverse of `parser::rle::format_r5g6b5_norm`; rounding back to the nearest
+/// channel value makes the pair lossless for every r5g6b5 colour.
+fn format_rgb_r5g6b5(r: u8, g: u8, b: u8) -> u16 {
+    let r = ((r as f32 / 255.0) * 31.0).round() as u16;
+    let g = ((g as f32 / 255.0) * 63.0).round() as u16;
+    let b = ((b as f32 / 255.0) * 31.0).round() as u16;
+    (r << 11) | (g << 5) | b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::rle::{parse_rle, parse_rle_as, parse_rle_with, format_r5g6b5_norm};
+
+    /// Builds a resource from r5g6b5 colours, where `None` is a transparent pixel.
+    fn make_resource(index: u32, width: i32, height: i32, px: &[Option<u16>]) -> Resource {
+        let mut resource = Resource::new();
+        resource.set_index(index);
+        resource.offset_x = -3 * index as i32;
+        resource.offset_y = 7;
+        resource.width = width;
+        resource.height = height;
+        resource.unknown_1 = 0x11 + index;
+        resource.unknown_2 = 0x22;
+        resource.unknown_3 = 0x33;
+        resource.unknown_4 = 0xDEAD_BEEF;
+        for p in px {
+            match *p {
+                Some(c) => {
+                    let (r, g, b) = format_r5g6b5_norm(c);
+                    resource.image_raw.extend_from_slice(&[r, g, b, 0xFF]);
+                }
+                None => resource.image_raw.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+        resource
+    }
+
+    fn assert_same_resource(a: &Resource, b: &Resource) {
+        assert_eq!(a.index(), b.index());
+        assert_eq!(a.offset_x, b.offset_x);
+        assert_eq!(a.offset_y, b.offset_y);
+        assert_eq!(a.width, b.width);
+        assert_eq!(a.height, b.height);
+        assert_eq!(a.unknown_1, b.unknown_1);
+        assert_eq!(a.unknown_2, b.unknown_2);
+        assert_eq!(a.unknown_3, b.unknown_3);
+        assert_eq!(a.unknown_4, b.unknown_4);
+        assert_eq!(a.image_raw, b.image_raw);
+    }
+
+    #[test]
+    fn test_r5g6b5_conversion_is_lossless() {
+        for c in 0..=0xFFFFu16 {
+            let (r, g, b) = format_r5g6b5_norm(c);
+            assert_eq!(c, format_rgb_r5g6b5(r, g, b));
+        }
+    }
+
+    #[test]
+    fn test_write_rle_round_trip() {
+        let n = None;
+        let mut rf = ResourceFile::new();
+        rf.resources.push(make_resource(0, 4, 3, &[
+            Some(0xF800), Some(0x07E0), Some(0x001F), Some(0xFFFF),
+            n,            Some(0x0000), n,            Some(0x1234),
+            n,            n,            n,            n,
+        ]));
+        // index 1 is left empty on purpose
+        rf.resources.push(make_resource(2, 5, 4, &[
+            n,            n,            Some(0xABCD), n,            n,
+            Some(0x8410), Some(0x8410), Some(0x8410), Some(0x8410), Some(0x8410),
+            n,            n,            n,            n,            Some(0x0001),
+            Some(0xF81F), n,            n,            n,            n,
+        ]));
+
+        let data = write_rle(&rf).unwrap();
+        let parsed = parse_rle(42, &data).unwrap();
+
+        assert_eq!(parsed.file_number, 42);
+        assert_eq!(parsed.resources.len(), rf.resources.len());
+        for (a, b) in rf.resources.iter().zip(parsed.resources.iter()) {
+            assert_eq!(b.file_num, Some(42));
+            assert_same_resource(a, b);
+        }
+
+        // re-encoding the parsed file gives the same bytes again
+        assert_eq!(data, write_rle(&parsed).unwrap());
+    }
+
+    #[test]
+    fn test_write_rle_r5g6b5_round_trip() {
+        let mut rf = ResourceFile::new();
+        let mut resource = Resource::new();
+        resource.width = 3;
+        resource.height = 2;
+        resource.pixel_format = PixelFormat::R5g6b5;
+        resource.image_raw = vec![0x1F, 0xF8, 0, 0, 0xE0, 0x07, 0, 0, 0, 0, 0x34, 0x12];
+        resource.mask = vec![0xFF, 0x00, 0xFF, 0xFF, 0x00, 0xFF];
+        rf.resources.push(resource);
+
+        let data = write_rle(&rf).unwrap();
+        let parsed = parse_rle_as(0, &data, PixelFormat::R5g6b5).unwrap();
+        assert_eq!(parsed.resources[0].image_raw, rf.resources[0].image_raw);
+        assert_eq!(parsed.resources[0].mask, rf.resources[0].mask);
+        assert_eq!(data, write_rle(&parsed).unwrap());
+
+        // the same file decoded to RGBA encodes to the same bytes
+        let rgba = parse_rle(0, &data).unwrap();
+        assert_eq!(&rgba.resources[0].image_raw[..8], &[0xFF, 0x00, 0xFF, 0xFF, 0, 0, 0, 0]);
+        assert_eq!(data, write_rle(&rgba).unwrap());
+    }
+
+    #[test]
+    fn test_write_rle_color_key() {
+        let magenta = ColorKey::MAGENTA;
+        let mut rf = ResourceFile::new();
+        rf.resources.push(make_resource(0, 3, 1, &[Some(0x1234), Some(magenta), Some(0x0001)]));
+
+        // without a key the magenta pixel is kept as a painted pixel
+        let data = write_rle(&rf).unwrap();
+        assert_same_resource(&parse_rle(0, &data).unwrap().resources[0], &rf.resources[0]);
+
+        // with it, the pixel is left out and the file decodes the same either way
+        let keyed = write_rle_with(&rf, ColorKey::Magenta).unwrap();
+        assert_ne!(keyed, data);
+        let plain = parse_rle(0, &keyed).unwrap();
+        let decoded = parse_rle_with(0, &data, PixelFormat::Rgba8888, ColorKey::Magenta).unwrap();
+        assert_eq!(plain.resources[0].image_raw, decoded.resources[0].image_raw);
+        assert_eq!(&plain.resources[0].image_raw[4..8], &[0, 0, 0, 0]);
+        assert_eq!(keyed, write_rle(&decoded).unwrap());
+    }
+
+    #[test]
+    fn test_write_rle_empty_file() {
+        let data = write_rle(&ResourceFile::new()).unwrap();
+        let parsed = parse_rle(0, &data).unwrap();
+        assert!(parsed.resources.is_empty());
+    }
+
+    #[test]
+    fn test_write_rle_rejects_bad_image_size() {
+        let mut rf = ResourceFile::new();
+        let mut resource = make_resource(0, 2, 2, &[None; 4]);
+        resource.image_raw.pop();
+        rf.resources.push(resource);
+        match write_rle(&rf) {
+            Err(Error::InvalidImageSize(0)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+        }
+    }
+
+    #[test]
+    fn test_write_rle_rejects_duplicate_index() {
+        let mut rf = ResourceFile::new();
+        rf.resources.push(make_resource(1, 1, 1, &[Some(1)]));
+        rf.resources.push(make_resource(1, 1, 1, &[Some(2)]));
+        match write_rle(&rf) {
+            Err(Error::DuplicateResourceIndex(1)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Serializes an `Rmd` into the data file layout described in `parser::rmd`.
+//!
+//! The entry, image, image id and animation counts are written from the
+//! lengths of the stored lists, so an `Rmd` that was edited after loading
+//! can't produce a file whose counts disagree with its contents.
+
+use byteorder::WriteBytesExt;
+use byteorder::LittleEndian as LE;
+
+use crate::error::Error;
+use crate::entity::rmd::Rmd;
+use crate::utility::writing::write_text;
+
+pub fn write_rmd(rmd: &Rmd) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::<u8>::new();
+
+    // header
+    write_text(&mut data, rmd.file_type())?;
+    data.write_u32::<LE>(rmd.file_number())?;
+    let padding = rmd.padding();
+    data.write_u32::<LE>(padding[0])?;
+    data.write_u32::<LE>(padding[1])?;
+    write_text(&mut data, rmd.string_1())?;
+    data.write_i32::<LE>(rmd.animation_parts())?;
+    data.write_i32::<LE>(rmd.animation_entry_count())?;
+    write_text(&mut data, rmd.string_2())?;
+
+    // entries
+    data.write_i32::<LE>(rmd.entries().len() as i32)?;
+    for entry in rmd.entries() {
+        data.write_i32::<LE>(entry.images().len() as i32)?;
+        for img in entry.images() {
+            data.write_i32::<LE>(img.source_x1)?;
+            data.write_i32::<LE>(img.source_y1)?;
+            data.write_i32::<LE>(img.source_x2)?;
+            data.write_i32::<LE>(img.source_y2)?;
+            data.write_i32::<LE>(img.empty_1)?;
+            data.write_i32::<LE>(img.empty_2)?;
+            data.write_i32::<LE>(img.dest_x)?;
+            data.write_i32::<LE>(img.dest_y)?;
+            data.write_i32::<LE>(img.render_z)?;
+            data.write_i32::<LE>(img.draw_type)?;
+            data.write_i32::<LE>(img.image_id.len() as i32)?;
+            for id in img.image_id.iter() {
+                data.write_i32::<LE>(*id)?;
+            }
+        }
+    }
+
+    // animations
+    data.write_i32::<LE>(rmd.animations().len() as i32)?;
+    for ani in rmd.animations() {
+        data.write_i32::<LE>(ani.frames().len() as i32)?;
+        for frame in ani.frames() {
+            data.write_i16::<LE>(*frame)?;
+        }
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::rmd_builder::RmdBuilder;
+    use crate::entity::rmd_image::RmdImage;
+    use crate::entity::rmd_type::RmdType;
+    use crate::parser::rmd::parse_rmd;
+
+    fn make_rmd() -> Rmd {
+        RmdBuilder::new(RmdType::Character)
+            .file_number(42)
+            // "한글" in cp949 with some NUL padding
+            .strings(&[0xC7, 0xD1, 0xB1, 0xDB, 0x00, 0x00], b"body")
+            .animation_parts(2)
+            .animation_entry_count(3)
+            .entry(vec![
+                RmdImage::new()
+                    .with_source(0, 0, 48, 24)
+                    .with_dest(-24, -12)
+                    .with_render_z(2)
+                    .with_draw_type(1)
+                    .with_image_ids(&[10, 11, 12]),
+                RmdImage::new()
+                    .with_source(4, 8, 12, 16)
+                    .with_image_ids(&[]),
+            ])
+            .entry(vec![])
+            .entry(vec![RmdImage::new().with_image_ids(&[-1])])
+            .animation(&[0, 2, 1])
+            .animation(&[])
+            .build()
+    }
+
+    #[test]
+    fn test_builder_counts() {
+        let rmd = make_rmd();
+        assert_eq!(rmd.entry_count(), 3);
+        assert_eq!(rmd.animation_count(), 2);
+        let entry = rmd.get_entry(0).unwrap();
+        assert_eq!(entry.image_count(), 2);
+        assert_eq!(entry.images()[0].image_id_count, 3);
+        assert_eq!(rmd.animations()[0].frame_count(), 3);
+        assert_eq!(rmd.string_1(), "한글");
+        assert_eq!(rmd.string_2(), "body");
+    }
+
+    #[test]
+    fn test_write_rmd_round_trip() {
+        let rmd = make_rmd();
+        let data = write_rmd(&rmd).unwrap();
+        let parsed = parse_rmd(RmdType::Character, &data).unwrap();
+        assert_eq!(rmd, parsed);
+        assert_eq!(data, write_rmd(&parsed).unwrap());
+    }
+
+    #[test]
+    fn test_write_rmd_rejects_long_string() {
+        let rmd = RmdBuilder::new(RmdType::Tile)
+            .strings(&[b'a'; 256], b"")
+            .build();
+        match write_rmd(&rmd) {
+            Err(Error::StringTooLong(256)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Serializes a `Map` back into the "RedMoon MapData 1.0" layout described
+//! in `parser::rmm`.
+//!
+//! Every tile is re-packed into its 8 byte form:
+//!
+//! b_0: bits 0-1 unknown_1, bits 2-7 obj file number (low 6 bits)
+//! b_1: bits 0-4 obj file number (high 5 bits), bits 5-7 tle file index (low 3 bits)
+//! b_2: bits 0-6 tle file index (high 7 bits), bit 7 tle file number (low bit)
+//! b_3: tle file number (high 8 bits)
+//! b_4: warp
+//! b_5: unknown_2
+//! b_6: collision
+//! b_7: obj file index >> 1 (the low bit is implied by `collision`)
+
+use byteorder::WriteBytesExt;
+use byteorder::LittleEndian as LE;
+
+use crate::error::Error;
+use crate::entity::map::Map;
+use crate::entity::map_tile::MapTile;
+use crate::utility::writing::write_text;
+use crate::utility::writing::write_u8_vec;
+
+const RMM_IDENTIFIER: &str = "RedMoon MapData 1.0";
+
+pub fn write_rmm(map: &Map) -> Result<Vec<u8>, Error> {
+    // the parser reads `size_x` * `size_y` tiles
+    if map.tile_count() as u64 != map.size_x() as u64 * map.size_y() as u64 {
+        return Err(Error::InvalidMapSize {
+            size_x: map.size_x(),
+            size_y: map.size_y(),
+            tiles: map.tile_count(),
+        });
+    }
+
+    let mut data = Vec::<u8>::new();
+
+    // header
+    write_u8_vec(&mut data, RMM_IDENTIFIER.as_bytes())?;
+    data.write_u32::<LE>(map.size_x())?;
+    data.write_u32::<LE>(map.size_y())?;
+    write_text(&mut data, map.name())?;
+    data.write_u32::<LE>(map.number())?;
+
+    // event rectangles
+    data.write_u32::<LE>(map.events().len() as u32)?;
+    for event in map.events() {
+        data.write_u16::<LE>(event.number)?;
+        data.write_u32::<LE>(event.left)?;
+        data.write_u32::<LE>(event.top)?;
+        data.write_u32::<LE>(event.right)?;
+        data.write_u32::<LE>(event.bottom)?;
+    }
+
+    // tiles
+    for (idx, tile) in map.tiles().iter().enumerate() {
+        let bytes = pack_v1(tile).ok_or(Error::InvalidMapTile(idx))?;
+        data.extend_from_slice(&bytes);
+    }
+
+    Ok(data)
+}
+
+/// Inverse of `parser::rmm::parse_v1`; returns `None` if a field doesn't fit
+/// into its bit range.
+fn pack_v1(tile: &MapTile) -> Option<[u8; 8]> {
+    let obj_file_num = tile.obj_rmd_entry.file();
+    let obj_file_idx = tile.obj_rmd_entry.index();
+    let tle_file_num = tile.tle_rmd_entry.file();
+    let tle_file_idx = tile.tle_rmd_entry.index();
+
+    let obj_idx_low_bit = if tile.collision.is_multiple_of(24) { 0 } else { 1 };
+    if obj_file_num >= 1 << 11
+        || tle_file_idx >= 1 << 10
+        || tle_file_num >= 1 << 9
+        || obj_file_idx >= 1 << 9
+        || obj_file_idx & 0x1 != obj_idx_low_bit
+        || tile.warp > 0xFF
+        || tile.collision > 0xFF
+        || tile.unknown_1 & !0x3 != 0 {
+        return None;
+    }
+
+    Some([
+        ((obj_file_num & 0x3F) << 2) as u8 | tile.unknown_1,
+        ((obj_file_num >> 6) | ((tle_file_idx & 0x7) << 5)) as u8,
+        ((tle_file_idx >> 3) | ((tle_file_num & 0x1) << 7)) as u8,
+        (tle_file_num >> 1) as u8,
+        tile.warp as u8,
+        tile.unknown_2,
+        tile.collision as u8,
+        (obj_file_idx >> 1) as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::entry::Entry;
+    use crate::entity::event::Event;
+    use crate::parser::rmm::parse_rmm;
+
+    /// Builds a small map file by hand with pseudo-random tile bytes.
+    fn make_rmm_bytes(size_x: u32, size_y: u32) -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        data.push(RMM_IDENTIFIER.len() as u8);
+        data.extend_from_slice(RMM_IDENTIFIER.as_bytes());
+        data.write_u32::<LE>(size_x).unwrap();
+        data.write_u32::<LE>(size_y).unwrap();
+        data.extend_from_slice(&[3, 0xB8, 0xB6, 0x00]);
+        data.write_u32::<LE>(7).unwrap();
+        // two events, the second one being an empty placeholder
+        data.write_u32::<LE>(2).unwrap();
+        for &(number, l, t, r, b) in [(5u16, 1, 2, 3, 4), (0, 0, 0, 0, 0)].iter() {
+            data.write_u16::<LE>(number).unwrap();
+            for v in [l, t, r, b].iter() {
+                data.write_u32::<LE>(*v).unwrap();
+            }
+        }
+        let mut seed = 0x1234_5678u32;
+        for _ in 0..(size_x * size_y * 8) {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            data.push((seed >> 16) as u8);
+        }
+        // the second bit of each tile's first byte is never set
+        for tile in 0..(size_x * size_y) as usize {
+            let idx = data.len() - (size_x * size_y) as usize * 8 + tile * 8;
+            data[idx] &= !0x2;
+        }
+        data
+    }
+
+    #[test]
+    fn test_write_rmm_byte_exact() {
+        let data = make_rmm_bytes(5, 3);
+        let map = parse_rmm(&data).unwrap();
+        assert_eq!(map.tile_count(), 15);
+        assert_eq!(map.events().len(), 2);
+        // the name is cp949 text padded with a NUL byte, which is written back as is
+        assert_eq!(map.name().as_str(), "마");
+        assert_eq!(map.name().raw(), &[0xB8, 0xB6, 0x00]);
+        assert_eq!(data, write_rmm(&map).unwrap());
+    }
+
+    #[test]
+    fn test_parse_rmm_reports_offsets() {
+        use crate::entity::file_kind::FileKind;
+
+        let mut data = make_rmm_bytes(5, 3);
+        let second_tile = data.len() - 14 * 8;
+        data[second_tile] |= 0x2;
+        match parse_rmm(&data) {
+            Err(Error::UnexpectedValue { kind: FileKind::Rmm, offset, .. }) => {
+                assert_eq!(offset, second_tile as u64);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|m| m.tile_count())),
+        }
+        data[second_tile] &= !0x2;
+        let len = data.len() - 3;
+        match parse_rmm(&data[..len]) {
+            Err(Error::UnexpectedEof { kind: FileKind::Rmm, .. }) => (),
+            other => panic!("unexpected result: {:?}", other.map(|m| m.tile_count())),
+        }
+    }
+
+    #[test]
+    fn test_write_rmm_round_trip_tiles() {
+        let mut map = Map::new();
+        map.set_size_x(2);
+        map.set_size_y(1);
+        map.set_map_number(12);
+        map.add_event(Event { number: 1, left: 10, top: 20, right: 30, bottom: 40 });
+        map.add_tile(MapTile {
+            obj_rmd_entry: Entry::new(2047, 510),
+            tle_rmd_entry: Entry::new(511, 1023),
+            warp: 16,
+            collision: 48,
+            unknown_1: 0x1,
+            unknown_2: 0xAA,
+        });
+        map.add_tile(MapTile {
+            obj_rmd_entry: Entry::new(0, 1),
+            tle_rmd_entry: Entry::new(1, 0),
+            warp: 0,
+            collision: 1,
+            unknown_1: 0,
+            unknown_2: 0,
+        });
+
+        let data = write_rmm(&map).unwrap();
+        let parsed = parse_rmm(&data).unwrap();
+        assert_eq!(parsed.number(), 12);
+        assert_eq!(parsed.size_x(), 2);
+        assert_eq!(parsed.size_y(), 1);
+        assert_eq!(parsed.events().len(), 1);
+        assert_eq!(parsed.tiles(), map.tiles());
+    }
+
+    #[test]
+    fn test_write_rmm_rejects_unrepresentable_tile() {
+        let mut map = Map::new();
+        map.set_size_x(1);
+        map.set_size_y(1);
+        map.add_tile(MapTile {
+            obj_rmd_entry: Entry::new(1, 2),
+            tle_rmd_entry: Entry::new(1, 1),
+            warp: 0,
+            collision: 1, // implies an odd object index
+            unknown_1: 0,
+            unknown_2: 0,
+        });
+        match write_rmm(&map) {
+            Err(Error::InvalidMapTile(0)) => (),
+            other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+        }
+    }
+
+    #[test]
+    fn test_write_rmm_rejects_wrong_size() {
+        let mut map = parse_rmm(&make_rmm_bytes(2, 2)).unwrap();
+        map.set_size_y(3);
+        match write_rmm(&map) {
+            Err(Error::InvalidMapSize { size_x: 2, size_y: 3, tiles: 4 }) => (),
+            other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Compares `cp949_to_utf8` with the linear table scan it replaced, run with `cargo bench`.
+//!
+//! The sample is every double byte character of the table followed by some ascii, which is
+//! about what the strings of an event file look like, only longer.
+
+extern crate cp949;
+
+use std::time::{Duration, Instant};
+
+use cp949::character_table::CP949_TABLE;
+use cp949::cp949_to_utf8;
+
+const ITERATIONS: u32 = 20;
+
+/// The old decoder, which walked the table for every double byte character
+fn cp949_to_utf8_linear(input: &[u8]) -> String {
+    let mut output = String::new();
+    let mut idx = 0;
+    while idx < input.len() {
+        let uv = match input[idx] {
+            val @ 0x00 ..= 0x7F => val as u32,
+            val @ 0x81 ..= 0xFE if idx + 1 < input.len() => {
+                idx += 1;
+                let c = ((val as u16) << 8) + input[idx] as u16;
+                CP949_TABLE.iter()
+                    .find(|entry| entry.cv == c)
+                    .map(|entry| entry.uv as u32)
+                    .unwrap_or(0xFFFD)
+            }
+            _ => 0xFFFD,
+        };
+        idx += 1;
+        output.push(std::char::from_u32(uv).unwrap());
+    }
+    output
+}
+
+fn sample() -> Vec<u8> {
+    let mut input = Vec::<u8>::new();
+    let characters = CP949_TABLE.iter().filter(|entry| entry.cv > 0xFF);
+    for (n, entry) in characters.enumerate() {
+        input.push((entry.cv >> 8) as u8);
+        input.push(entry.cv as u8);
+        if n % 32 == 31 {
+            input.extend_from_slice(b" RedMoon 3.9 ");
+        }
+    }
+    input
+}
+
+fn bench<F: Fn(&[u8]) -> String>(name: &str, input: &[u8], decode: F) -> String {
+    let mut output = decode(input);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        output = decode(input);
+    }
+    let per_run = start.elapsed() / ITERATIONS;
+    println!("{:<8} {:>10.1} us per run, {:>8.1} MB/s",
+             name, micros(per_run), input.len() as f64 / micros(per_run));
+    output
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1e6 + duration.subsec_nanos() as f64 / 1e3
+}
+
+fn main() {
+    let input = sample();
+    println!("decoding {} bytes, {} runs each", input.len(), ITERATIONS);
+    let linear = bench("linear", &input, cp949_to_utf8_linear);
+    let indexed = bench("indexed", &input, cp949_to_utf8);
+    assert_eq!(linear, indexed);
+}
@@ -0,0 +1,76 @@
+//! Writes `src/index_table.rs` from `CP949_TABLE`, run it from the crate folder after the
+//! character table has changed:
+//!
+//! ```text
+//! cargo run --example gen_index_table > src/index_table.rs
+//! ```
+//!
+//! `test_index_matches_table` fails until the index has been written again.
+
+extern crate cp949;
+
+use std::fmt::Write;
+
+use cp949::character_table::CP949_TABLE;
+use cp949::index_table::{FIRST_LEAD, FIRST_TRAIL, LEAD_COUNT, TRAIL_COUNT};
+
+const HEADER: &str = "\
+//! The double byte characters of `CP949_TABLE`, arranged so they are found without walking
+//! the table: `CP949_INDEX` holds their unicode values indexed by the lead and trail bytes, a
+//! `0` marks a pair of bytes that isn't a character, and `UNICODE_INDEX` can be binary searched
+//! by the unicode value. Generated from `character_table.rs` by `examples/gen_index_table.rs`.
+
+/// The lead bytes are 0x81 to 0xFE
+pub const FIRST_LEAD: u8 = 0x81;
+pub const LAST_LEAD: u8 = 0xFE;
+pub const LEAD_COUNT: usize = (LAST_LEAD - FIRST_LEAD) as usize + 1;
+/// The trail bytes are 0x41 to 0xFE
+pub const FIRST_TRAIL: u8 = 0x41;
+pub const LAST_TRAIL: u8 = 0xFE;
+pub const TRAIL_COUNT: usize = (LAST_TRAIL - FIRST_TRAIL) as usize + 1;
+";
+
+/// Values per line of `CP949_INDEX`, a lead byte's row fills 19 lines
+const INDEX_PER_LINE: usize = 10;
+/// Pairs per line of `UNICODE_INDEX`
+const PAIRS_PER_LINE: usize = 5;
+
+fn main() {
+    let characters: Vec<(u16, u16)> = CP949_TABLE.iter()
+        .filter(|entry| entry.cv > 0xFF)
+        .map(|entry| (entry.cv, entry.uv))
+        .collect();
+
+    let mut index = vec![0u16; LEAD_COUNT * TRAIL_COUNT];
+    for &(cv, uv) in characters.iter() {
+        let (lead, trail) = ((cv >> 8) as u8, cv as u8);
+        let row = (lead - FIRST_LEAD) as usize;
+        index[row * TRAIL_COUNT + (trail - FIRST_TRAIL) as usize] = uv;
+    }
+
+    let mut by_unicode: Vec<(u16, u16)> = characters.iter().map(|&(cv, uv)| (uv, cv)).collect();
+    by_unicode.sort();
+
+    let mut out = String::from(HEADER);
+    out.push_str("\npub static CP949_INDEX: [u16; LEAD_COUNT * TRAIL_COUNT] = [\n");
+    for (row, values) in index.chunks(TRAIL_COUNT).enumerate() {
+        writeln!(out, "    // {:#04X}", FIRST_LEAD as usize + row).unwrap();
+        for line in values.chunks(INDEX_PER_LINE) {
+            let line: Vec<String> = line.iter().map(|uv| format!("0x{:04X}", uv)).collect();
+            writeln!(out, "    {},", line.join(", ")).unwrap();
+        }
+    }
+    out.push_str("];\n");
+
+    out.push_str("\n/// The double byte characters as `(unicode, cp949)` pairs, sorted by their unicode value\n");
+    writeln!(out, "pub static UNICODE_INDEX: [(u16, u16); {}] = [", by_unicode.len()).unwrap();
+    for line in by_unicode.chunks(PAIRS_PER_LINE) {
+        let line: Vec<String> = line.iter()
+            .map(|&(uv, cv)| format!("(0x{:04X}, 0x{:04X})", uv, cv))
+            .collect();
+        writeln!(out, "    {},", line.join(", ")).unwrap();
+    }
+    out.push_str("];\n");
+
+    print!("{}", out);
+}
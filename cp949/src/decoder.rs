@@ -0,0 +1,216 @@
+//! Decoding of cp949 text that arrives in pieces, like network packets or a file that is read
+//! in chunks. A double byte character can be split over two pieces, so the decoder keeps a
+//! lead byte at the end of one input until the next one brings its trail byte.
+
+use std::io;
+use std::io::Read;
+
+use crate::DecodeError;
+use crate::lookup_949_char;
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+const READ_BUFFER_LEN: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    strict: bool,
+    // a lead byte at the end of the last input
+    lead: Option<u8>,
+    // the bytes of all of the earlier inputs
+    offset: usize,
+}
+
+impl Decoder {
+    /// Replaces every invalid sequence with U+FFFD
+    pub fn lossy() -> Decoder {
+        Decoder { strict: false, lead: None, offset: 0 }
+    }
+
+    /// Fails on the first invalid sequence; the decoder shouldn't be used after that
+    pub fn strict() -> Decoder {
+        Decoder { strict: true, lead: None, offset: 0 }
+    }
+
+    /// Decodes the next piece of the input into `output`
+    pub fn decode(&mut self, input: &[u8], output: &mut String) -> Result<(), DecodeError> {
+        output.reserve(input.len());
+        let mut idx = 0;
+        if let Some(lead) = self.lead.take() {
+            match input.first() {
+                Some(&trail) => self.push(lookup_949_char(lead, trail), self.offset - 1, output)?,
+                None => self.lead = Some(lead),
+            }
+            idx = 1;
+        }
+
+        while idx < input.len() {
+            let start = idx;
+            let c = match input[idx] {
+                val @ 0x00 ..= 0x7F => Some(val as char),
+                0x80 | 0xFF         => None, // undefined values
+                val @ 0x81 ..= 0xFE => {
+                    // lead byte encountered, the trail byte may be in the next input
+                    if idx + 1 == input.len() {
+                        self.lead = Some(val);
+                        break;
+                    }
+                    idx += 1;
+                    lookup_949_char(val, input[idx])
+                }
+            };
+            idx += 1;
+            self.push(c, self.offset + start, output)?;
+        }
+        self.offset += input.len();
+        Ok(())
+    }
+
+    /// Ends the input; a lead byte that is still waiting for its trail byte is invalid
+    pub fn finish(&mut self, output: &mut String) -> Result<(), DecodeError> {
+        match self.lead.take() {
+            Some(_) => self.push(None, self.offset - 1, output),
+            None => Ok(()),
+        }
+    }
+
+    fn push(&self, c: Option<char>, index: usize, output: &mut String) -> Result<(), DecodeError> {
+        match c {
+            Some(c) => output.push(c),
+            None if self.strict => return Err(DecodeError { index }),
+            None => output.push(REPLACEMENT_CHARACTER),
+        }
+        Ok(())
+    }
+}
+
+/// Reads cp949 text from `inner` as utf8. In strict mode an invalid sequence is an
+/// `InvalidData` error holding the `DecodeError`.
+pub struct DecodeReader<R> {
+    inner: R,
+    decoder: Decoder,
+    decoded: Vec<u8>,
+    pos: usize,
+    finished: bool,
+}
+
+impl<R: Read> DecodeReader<R> {
+    pub fn new(inner: R, decoder: Decoder) -> DecodeReader<R> {
+        DecodeReader {
+            inner,
+            decoder,
+            decoded: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decodes the next chunk of `inner`, returns false at the end of the input
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.finished {
+            return Ok(false);
+        }
+        let mut input = [0u8; READ_BUFFER_LEN];
+        let len = self.inner.read(&mut input)?;
+        let mut text = String::new();
+        let result = if len == 0 {
+            self.finished = true;
+            self.decoder.finish(&mut text)
+        } else {
+            self.decoder.decode(&input[..len], &mut text)
+        };
+        result.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.decoded = text.into_bytes();
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.decoded.len() - self.pos);
+        buf[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cp949_to_utf8;
+
+    // "레드문" and "한글", some ascii and an invalid pair in between
+    const INPUT: &[u8] = &[
+        0xB7, 0xB9, 0xB5, 0xE5, 0xB9, 0xAE, b' ', 0x81, 0x20, b'v', b'3', 0xC7, 0xD1, 0xB1, 0xDB,
+    ];
+
+    /// Reads one byte at a time, to split every double byte character
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_decoder_split_input() {
+        let expected = "레드문 \u{FFFD}v3한글";
+        assert_eq!(cp949_to_utf8(INPUT), expected);
+        for split in 0..INPUT.len() + 1 {
+            let mut decoder = Decoder::lossy();
+            let mut output = String::new();
+            decoder.decode(&INPUT[..split], &mut output).unwrap();
+            decoder.decode(&[], &mut output).unwrap();
+            decoder.decode(&INPUT[split..], &mut output).unwrap();
+            decoder.finish(&mut output).unwrap();
+            assert_eq!(output, expected, "split at {}", split);
+        }
+
+        let mut decoder = Decoder::strict();
+        let mut output = String::new();
+        decoder.decode(&INPUT[..4], &mut output).unwrap();
+        assert_eq!(output, "레드");
+        decoder.decode(&INPUT[4..8], &mut output).unwrap();
+        // the lead byte of the invalid pair came with the last input
+        assert_eq!(decoder.decode(&INPUT[8..], &mut output), Err(DecodeError { index: 7 }));
+        assert_eq!(output, "레드문 ");
+
+        let mut decoder = Decoder::strict();
+        let mut output = String::new();
+        decoder.decode(&INPUT[11..14], &mut output).unwrap();
+        assert_eq!(decoder.finish(&mut output), Err(DecodeError { index: 2 }));
+    }
+
+    #[test]
+    fn test_decode_reader() {
+        let mut output = String::new();
+        DecodeReader::new(Trickle(INPUT), Decoder::lossy()).read_to_string(&mut output).unwrap();
+        assert_eq!(output, cp949_to_utf8(INPUT));
+
+        let valid = &INPUT[9..];
+        let mut output = String::new();
+        DecodeReader::new(valid, Decoder::strict()).read_to_string(&mut output).unwrap();
+        assert_eq!(output, "v3한글");
+
+        let mut reader = DecodeReader::new(Trickle(INPUT), Decoder::strict());
+        let err = reader.read_to_string(&mut String::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = err.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+        assert_eq!(*err, DecodeError { index: 7 });
+    }
+}
@@ -1,58 +1,196 @@
-extern crate byteorder;
-
 pub mod character_table;
+pub mod decoder;
+pub mod index_table;
 
-use std::io::Cursor;
+use std::error;
+use std::fmt;
 
-use byteorder::ReadBytesExt;
+use crate::index_table::{CP949_INDEX, FIRST_LEAD, LAST_LEAD, FIRST_TRAIL, LAST_TRAIL, TRAIL_COUNT};
+use crate::index_table::UNICODE_INDEX;
+use crate::decoder::Decoder;
 
-use crate::character_table::CP949_TABLE;
+/// What `utf8_to_cp949_lossy` writes for the characters cp949 doesn't have
+pub const REPLACEMENT_BYTE: u8 = b'?';
 
-const REPLACEMENT_CHARACTER: u32 = 0xFFFD;
+/// A character of the input that cp949 can't encode, `index` is its byte offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeError {
+    pub character: char,
+    pub index: usize,
+}
 
-fn lookup_949_char(input: u16) -> u32 {
-    for entry in CP949_TABLE.iter() {
-        if entry.cv == input {
-            return entry.uv as u32
-        }
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} at byte {} has no cp949 encoding", self.character, self.index)
+    }
+}
+
+impl error::Error for EncodeError {}
+
+/// The input isn't valid cp949, `index` is the byte offset of the first invalid sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub index: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid cp949 sequence at byte {}", self.index)
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// The double byte character of a lead and a trail byte, `None` if there isn't one
+pub(crate) fn lookup_949_char(lead: u8, trail: u8) -> Option<char> {
+    if !(FIRST_LEAD..=LAST_LEAD).contains(&lead) || !(FIRST_TRAIL..=LAST_TRAIL).contains(&trail) {
+        return None;
+    }
+    let idx = (lead - FIRST_LEAD) as usize * TRAIL_COUNT + (trail - FIRST_TRAIL) as usize;
+    match CP949_INDEX[idx] {
+        0 => None,
+        uv => std::char::from_u32(uv as u32),
     }
-    REPLACEMENT_CHARACTER
 }
 
+/// Decodes the text, every invalid sequence becomes a U+FFFD replacement character
 pub fn cp949_to_utf8(input: &[u8]) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut decoder = Decoder::lossy();
+    // the lossy decoder doesn't fail
+    let _ = decoder.decode(input, &mut output).and_then(|_| decoder.finish(&mut output));
+    output
+}
 
-    let mut output = String::new();
-    let mut cursor = Cursor::new(input);
-    let input_len = input.len() as u64;
-
-    while cursor.position() < input_len {
-        let idx = cursor.position();
-        let uni_code_point: u32 = match cursor.read_u8().unwrap() as u32 {
-            val @ 0x00 ... 0x7F => val as u32,
-            0x80 | 0xFF         => REPLACEMENT_CHARACTER, // undefined values
-            val @ 0x81 ... 0xFE => {
-                // lead byte encountered
-                if idx + 1 < input_len {
-                    let next = cursor.read_u8().unwrap() as u16;
-                    let mut c: u16 = ((val as u16) << 8) + next;
-                    lookup_949_char(c) as u32
-                } else {
-                    REPLACEMENT_CHARACTER
-                }
-            },
-            _ => REPLACEMENT_CHARACTER
-        };
-        let c = std::char::from_u32(uni_code_point).unwrap();
-        output.push(c);
+/// Decodes the text, failing on the first invalid sequence
+pub fn cp949_to_utf8_strict(input: &[u8]) -> Result<String, DecodeError> {
+    let mut output = String::with_capacity(input.len());
+    let mut decoder = Decoder::strict();
+    decoder.decode(input, &mut output)?;
+    decoder.finish(&mut output)?;
+    Ok(output)
+}
+
+/// The cp949 bytes of a character, `None` if cp949 doesn't have it
+fn lookup_unicode_char(c: char) -> Option<[u8; 2]> {
+    let uv = c as u32;
+    if uv > 0xFFFF {
+        return None;
+    }
+    UNICODE_INDEX.binary_search_by_key(&(uv as u16), |&(uv, _)| uv)
+        .ok()
+        .map(|idx| {
+            let cv = UNICODE_INDEX[idx].1;
+            [(cv >> 8) as u8, cv as u8]
+        })
+}
+
+/// Encodes the text as cp949, failing on the first character that cp949 doesn't have
+pub fn utf8_to_cp949(input: &str) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::<u8>::with_capacity(input.len());
+    for (index, character) in input.char_indices() {
+        if character.is_ascii() {
+            output.push(character as u8);
+        } else if let Some(bytes) = lookup_unicode_char(character) {
+            output.extend_from_slice(&bytes);
+        } else {
+            return Err(EncodeError { character, index });
+        }
     }
+    Ok(output)
+}
 
+/// Encodes the text as cp949, writing a `REPLACEMENT_BYTE` for every character that cp949
+/// doesn't have
+pub fn utf8_to_cp949_lossy(input: &str) -> Vec<u8> {
+    let mut output = Vec::<u8>::with_capacity(input.len());
+    for character in input.chars() {
+        if character.is_ascii() {
+            output.push(character as u8);
+        } else if let Some(bytes) = lookup_unicode_char(character) {
+            output.extend_from_slice(&bytes);
+        } else {
+            output.push(REPLACEMENT_BYTE);
+        }
+    }
     output
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::character_table::CP949_TABLE;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn test_index_matches_table() {
+        // regenerate the index with `examples/gen_index_table.rs` when this fails
+        let mut count = 0;
+        for entry in CP949_TABLE.iter().filter(|entry| entry.cv > 0xFF) {
+            let (lead, trail) = ((entry.cv >> 8) as u8, entry.cv as u8);
+            let character = lookup_949_char(lead, trail).map(|c| c as u32);
+            assert_eq!(character, Some(entry.uv as u32), "{:#06x}", entry.cv);
+            count += 1;
+        }
+        let mapped = CP949_INDEX.iter().filter(|&&uv| uv != 0).count();
+        assert_eq!(mapped, count);
+    }
+
+    #[test]
+    fn test_cp949_to_utf8() {
+        // "레드문 v3.9"
+        let input = [0xB7, 0xB9, 0xB5, 0xE5, 0xB9, 0xAE, b' ', b'v', b'3', b'.', b'9'];
+        assert_eq!(cp949_to_utf8(&input), "레드문 v3.9");
+        // undefined bytes, a pair that isn't a character and a lone lead byte
+        let input = [0x80, b'a', 0x81, 0x20, 0xFF, 0xB7];
+        assert_eq!(cp949_to_utf8(&input), "\u{FFFD}a\u{FFFD}\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_cp949_to_utf8_strict() {
+        let input = [0xB7, 0xB9, b' ', 0xB5, 0xE5];
+        assert_eq!(cp949_to_utf8_strict(&input).unwrap(), "레 드");
+        assert_eq!(cp949_to_utf8_strict(&[b'a', 0x80]), Err(DecodeError { index: 1 }));
+        assert_eq!(cp949_to_utf8_strict(&[b'a', 0xB7, 0x20, 0xFF]), Err(DecodeError { index: 1 }));
+        // a lead byte without its trail byte
+        assert_eq!(cp949_to_utf8_strict(&input[..4]), Err(DecodeError { index: 3 }));
+    }
+
+    #[test]
+    fn test_round_trip_table() {
+        let characters = CP949_TABLE.iter()
+            .filter(|entry| entry.un != "UNDEFINED" && entry.un != "DBCS LEAD BYTE");
+        let mut count = 0;
+        for entry in characters {
+            let character = std::char::from_u32(entry.uv as u32).unwrap();
+            let bytes = if entry.cv > 0xFF {
+                vec![(entry.cv >> 8) as u8, entry.cv as u8]
+            } else {
+                vec![entry.cv as u8]
+            };
+            let text = character.to_string();
+            assert_eq!(utf8_to_cp949(&text).unwrap(), bytes, "{:#06x}", entry.cv);
+            assert_eq!(cp949_to_utf8(&bytes), text, "{:#06x}", entry.cv);
+            count += 1;
+        }
+        assert_eq!(count, 128 + UNICODE_INDEX.len());
+    }
+
+    #[test]
+    fn test_utf8_to_cp949() {
+        let bytes = [0xB7, 0xB9, 0xB5, 0xE5, 0xB9, 0xAE, b' ', b'v', b'3', b'.', b'9'];
+        assert_eq!(utf8_to_cp949("레드문 v3.9").unwrap(), bytes);
+        assert_eq!(utf8_to_cp949("").unwrap(), b"");
+
+        // neither the n with a tilde nor the emoji are in cp949
+        let input = "레드ñ문 🌙";
+        let err = utf8_to_cp949(input).unwrap_err();
+        assert_eq!(err, EncodeError { character: 'ñ', index: 6 });
+        assert_eq!(&input[err.index..], "ñ문 🌙");
+        assert_eq!(utf8_to_cp949_lossy(input), [0xB7, 0xB9, 0xB5, 0xE5, b'?', 0xB9, 0xAE, b' ', b'?']);
+    }
 }
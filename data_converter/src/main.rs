@@ -7,59 +7,69 @@ extern crate xml_writer;
 use std::path::Path;
 use std::path::PathBuf;
 use std::fs::File;
-use std::fs::read_dir;
 use std::io::Read;
-// use std::io::Write;
+use std::io::Write;
 use std::io::BufWriter;
+use std::rc::Rc;
 
 use png::HasParameters;
 
+use core_compat::entity::pixel_format::PixelFormat;
 use core_compat::entity::resource_file::ResourceFile;
 use core_compat::entity::resource::Resource;
 use core_compat::entity::rmd::Rmd;
 use core_compat::entity::rmd_type::RmdType;
 use core_compat::entity::map::Map;
 use core_compat::entity::list::List;
+use core_compat::entity::sound::{Sound, SampleFormat};
+use core_compat::entity::sprite_type::SpriteType;
 use core_compat::error::Error;
-use core_compat::parser::rle::parse_rle;
+use core_compat::parser::rle::parse_rle_lenient_with;
 use core_compat::parser::rmd::parse_rmd;
 use core_compat::parser::rmm::parse_rmm;
-use core_compat::parser::lst::parse_lst;
+use core_compat::parser::lst::parse_lst_lenient;
+use core_compat::parser::rms::{parse_rms, sound_path};
+use core_compat::repository::game_data::GameData;
+use core_compat::repository::vfs::Vfs;
 
 static OUTPUT_PATH: &'static str = "../temp/";
 
+// The game files are looked up here, the source paths below are relative to it
+static DATA_PATH: &'static str = "../data/";
+
 // This is the list of data folder's and list files for them
-static RLE_ENTRIES: [(&'static str, &'static str, &'static str, &'static str, bool); 16] = [
-    // type      |short| source path           | source list path          | type 2?
-    ("bullets",   "bul", "../data/RLEs/Bul",     "../data/RLEs/bul.lst",     false),
-    ("icons",     "ico", "../data/RLEs/Ico",     "../data/RLEs/ico.lst",     false),
-    ("objects",   "obj", "../data/RLEs/Obj",     "../data/RLEs/obj.lst",     true),
-    ("tiles",     "tle", "../data/RLEs/Tle",     "../data/RLEs/tle.lst",     false),
-    ("interface", "int", "../data/RLEs/Int",     "../data/RLEs/int.lst",     false),
-    ("philar",    "ch0", "../data/RLEs/Chr/C00", "../data/RLEs/Chr/c00.lst", false),
-    ("azlar",     "ch1", "../data/RLEs/Chr/C01", "../data/RLEs/Chr/c01.lst", false),
-    ("sadad",     "ch2", "../data/RLEs/Chr/C02", "../data/RLEs/Chr/c02.lst", false),
-    ("destino",   "ch3", "../data/RLEs/Chr/C03", "../data/RLEs/Chr/c03.lst", false),
-    ("jarexx",    "ch4", "../data/RLEs/Chr/C04", "../data/RLEs/Chr/c04.lst", false),
-    ("canon",     "ch5", "../data/RLEs/Chr/C05", "../data/RLEs/Chr/c05.lst", false),
-    ("kitara",    "ch6", "../data/RLEs/Chr/C06", "../data/RLEs/Chr/c06.lst", false),
-    ("lunarena",  "ch7", "../data/RLEs/Chr/C07", "../data/RLEs/Chr/c07.lst", false),
-    ("lavita",    "ch8", "../data/RLEs/Chr/C08", "../data/RLEs/Chr/c08.lst", false),
-    ("ch_9_gm",   "ch9", "../data/RLEs/Chr/C09", "../data/RLEs/Chr/c09.lst", false),
-    ("extra_chr", "etc", "../data/RLEs/Chr/Etc", "../data/RLEs/Chr/etc.lst", false),
-    // The sounds one is the only one which is a little different...
-    // ("Sounds", "snd", "../data/RLEs/Snd", "../data/RLEs/snd.lst"),
+static RLE_ENTRIES: [(&'static str, &'static str, &'static str, &'static str, SpriteType); 16] = [
+    // type      |short| source path   | source list path   | sprite type
+    ("bullets",   "bul", "RLEs/Bul",     "RLEs/bul.lst",      SpriteType::Bullet),
+    ("icons",     "ico", "RLEs/Ico",     "RLEs/ico.lst",      SpriteType::Icon),
+    ("objects",   "obj", "RLEs/Obj",     "RLEs/obj.lst",      SpriteType::Object),
+    ("tiles",     "tle", "RLEs/Tle",     "RLEs/tle.lst",      SpriteType::Tile),
+    ("interface", "int", "RLEs/Int",     "RLEs/int.lst",      SpriteType::Interface),
+    ("philar",    "ch0", "RLEs/Chr/C00", "RLEs/Chr/c00.lst",  SpriteType::Character),
+    ("azlar",     "ch1", "RLEs/Chr/C01", "RLEs/Chr/c01.lst",  SpriteType::Character),
+    ("sadad",     "ch2", "RLEs/Chr/C02", "RLEs/Chr/c02.lst",  SpriteType::Character),
+    ("destino",   "ch3", "RLEs/Chr/C03", "RLEs/Chr/c03.lst",  SpriteType::Character),
+    ("jarexx",    "ch4", "RLEs/Chr/C04", "RLEs/Chr/c04.lst",  SpriteType::Character),
+    ("canon",     "ch5", "RLEs/Chr/C05", "RLEs/Chr/c05.lst",  SpriteType::Character),
+    ("kitara",    "ch6", "RLEs/Chr/C06", "RLEs/Chr/c06.lst",  SpriteType::Character),
+    ("lunarena",  "ch7", "RLEs/Chr/C07", "RLEs/Chr/c07.lst",  SpriteType::Character),
+    ("lavita",    "ch8", "RLEs/Chr/C08", "RLEs/Chr/c08.lst",  SpriteType::Character),
+    ("ch_9_gm",   "ch9", "RLEs/Chr/C09", "RLEs/Chr/c09.lst",  SpriteType::Character),
+    ("extra_chr", "etc", "RLEs/Chr/Etc", "RLEs/Chr/etc.lst",  SpriteType::Character),
 ];
 
+static RMS_ENTRY: (&'static str, &'static str, &'static str) =
+    ("sounds",    "snd", "RLEs/snd.lst");
+
 static RMM_ENTRY: (&'static str, &'static str) =
-    ("maps", "../data/DATAs/Map");
+    ("maps", "DATAs/Map");
 
 static RMD_ENTRIES: [(&'static str, &'static str, &'static str, RmdType); 5] = [
-    ("bullet", "bul", "../data/DATAs/Bul", RmdType::Bullet),
-    ("char",   "chr", "../data/DATAs/Chr", RmdType::Character),
-    ("icon",   "ico", "../data/DATAs/Ico", RmdType::Icon),
-    ("object", "obj", "../data/DATAs/Obj", RmdType::Object),
-    ("tile",   "tle", "../data/DATAs/Tle", RmdType::Tile),
+    ("bullet", "bul", "DATAs/Bul", RmdType::Bullet),
+    ("char",   "chr", "DATAs/Chr", RmdType::Character),
+    ("icon",   "ico", "DATAs/Ico", RmdType::Icon),
+    ("object", "obj", "DATAs/Obj", RmdType::Object),
+    ("tile",   "tle", "DATAs/Tle", RmdType::Tile),
 ];
 
 fn main() {
@@ -72,19 +82,30 @@ fn main() {
         Err(e) => println!("{:?}", e),
     }
 
+    // every argument is a mod folder that is laid over the game files
+    let mut vfs = Vfs::new(Path::new(DATA_PATH));
+    for overlay in std::env::args().skip(1) {
+        println!("Using overlay: {:?}", overlay);
+        vfs.add_overlay(Path::new(&overlay));
+    }
+    let vfs = Rc::new(vfs);
+
     // parse the list file and insert them into the database
-    convert_rle_data();
+    convert_rle_data(&vfs);
 
     // convert the maps ...
-    // convert_rmm_data();
+    // convert_rmm_data(&vfs);
 
     // ... and rmd files
-    // convert_rmd_data();
+    // convert_rmd_data(&vfs);
+
+    // ... and the sounds
+    // convert_rms_data(&vfs);
 
     println!("finished!");
 }
 
-fn convert_rmd_data() {
+fn convert_rmd_data(vfs: &Vfs) {
     // create the output directory if it doesn't exist yet
     let mut data_out_dir = PathBuf::new();
     data_out_dir.push(OUTPUT_PATH);
@@ -97,19 +118,22 @@ fn convert_rmd_data() {
 
     // read every folder
     for &(kind, short, path, rmd_type) in RMD_ENTRIES.iter() {
-        let data_paths = read_dir(path).unwrap();
+        let data_paths = vfs.list(path);
 
         // read every file
-        for entry in data_paths {
-            let entry = entry.unwrap();
-            let path = entry.path();
-
-            let dat_file: Rmd = load_rmd_data(&path, rmd_type).unwrap();
+        for path in data_paths {
+            let dat_file: Rmd = match load_rmd_data(&path, rmd_type) {
+                Ok(dat_file) => dat_file,
+                Err(e) => {
+                    println!("{:?}: {}", path, e);
+                    continue
+                }
+            };
         }
     }
 }
 
-fn convert_rmm_data() {
+fn convert_rmm_data(vfs: &Rc<Vfs>) {
     // create the output directory if it doesn't exist yet
     let mut map_out_dir = PathBuf::new();
     map_out_dir.push(OUTPUT_PATH);
@@ -120,21 +144,20 @@ fn convert_rmm_data() {
         Err(e) => println!("{:?}", e),
     }
 
+    // follows the tiles to the sprites they draw
+    let mut game_data = GameData::with_vfs(vfs.clone());
+
     // book-keeping of map data paths
     let (kind, path) = RMM_ENTRY;
-    let map_path = Path::new(path);
-    let map_file_paths = read_dir(map_path).unwrap();
+    let map_file_paths = vfs.list(path);
 
     // parse the map files in the map directory
     let mut map_list: Vec<Map> = Vec::new();
-    for entry in map_file_paths {
-        let entry = entry.unwrap();
-        let path = entry.path();
+    for path in map_file_paths {
         let map: Map = match load_rmm_data(&path) {
             Ok(map) => map,
             Err(e) => {
-                println!("{:?}", e);
-                println!("{:?}", path);
+                println!("{:?}: {}", path, e);
                 continue
             }
         };
@@ -153,6 +176,12 @@ fn convert_rmm_data() {
         let file = File::create(&path_buf).unwrap();
         let writer = BufWriter::new(file);
 
+        // the sprites each tile draws
+        let map_sprites = game_data.resolve_map(&map);
+        for &((x, y), ref e) in map_sprites.errors() {
+            println!("map {} tile ({}, {}): {}", map.number(), x, y, e);
+        }
+
         let mut xml = xml_writer::XmlWriter::new(writer);
         xml.begin_elem("map").unwrap();
         // map number
@@ -181,12 +210,7 @@ fn convert_rmm_data() {
         }
         */
         // tiles
-        let mut x = 0;
-        let mut y = 0;
-        let max_x = map.size_x();
-        let max_y = map.size_y();
-
-        for tile in map.tiles() {
+        for ((x, y), tile) in map.enumerate_tiles() {
             // <tile>
             xml.begin_elem("tile").unwrap();
             // <x>
@@ -215,19 +239,22 @@ fn convert_rmm_data() {
             xml.begin_elem("collision").unwrap();
             xml.text(&format!("{}", tile.collision)).unwrap();
             xml.end_elem().unwrap();
+            // <sprite> the rle resources drawn for the tile, back to front
+            for sprite in map_sprites.tile_at(x, y) {
+                xml.begin_elem("sprite").unwrap();
+                xml.attr("type", &format!("{:?}", sprite.sprite_type).to_lowercase()).unwrap();
+                xml.attr("file", &format!("{}", sprite.rle_entry.file())).unwrap();
+                xml.attr("index", &format!("{}", sprite.rle_entry.index())).unwrap();
+                xml.attr("layer", &format!("{}", sprite.layer.raw())).unwrap();
+                xml.end_elem().unwrap();
+            }
             // </tile>
             xml.end_elem().unwrap();
-
-            // handle coordinate increments
-            x += 1;
-            if x >= max_x {
-                y += 1;
-                x = 0;
-            }
         }
 
-        if y != max_y {
-            println!("Map dimension mis-match: y:{}, max_y: {}", y, max_y);
+        if map.tile_count() != (map.size_x() * map.size_y()) as usize {
+            println!("Map dimension mis-match: tiles:{}, size: {}x{}",
+                     map.tile_count(), map.size_x(), map.size_y());
         }
 
         xml.close().unwrap();
@@ -235,8 +262,82 @@ fn convert_rmm_data() {
     }
 }
 
-fn convert_rle_data() {
-    for &(kind, short_kind, folder, list, use_v2) in RLE_ENTRIES.iter() {
+fn convert_rms_data(vfs: &Vfs) {
+    let (kind, short_kind, list) = RMS_ENTRY;
+    println!("file: {:?}", &kind);
+
+    // create a subfolder for the data if it doesn't exist
+    let mut out_dir = PathBuf::new();
+    out_dir.push(OUTPUT_PATH);
+    out_dir.push(short_kind);
+    println!("Creating directory: {:?}", out_dir);
+    match std::fs::create_dir(&out_dir) {
+        Ok(_) => (),
+        Err(e) => println!("{:?}", e),
+    }
+
+    // load the data from the list file
+    let list = match load_list_data(vfs, list) {
+        Ok(list) => list,
+        Err(e) => {
+            println!("{:?}: {}", list, e);
+            return
+        }
+    };
+    println!("list.items.len() == {:?}", list.items.len());
+
+    // every list item points at a single sound file
+    let mut matches = 0;
+    for item in &list.items {
+        let path = sound_path(&item.entry);
+        let sound = match load_rms_data(vfs, &path) {
+            Ok(sound) => sound,
+            Err(e) => {
+                println!("{:?}: {}", path, e);
+                continue
+            }
+        };
+        matches += 1;
+
+        let mut path_buf = out_dir.clone();
+        path_buf.push(format!("{}_{}.wav", short_kind, item.id));
+        println!("{:?}", &path_buf);
+        write_wav(&path_buf, &sound).unwrap();
+    }
+
+    println!("matches          == {:?}", matches);
+}
+
+fn write_wav(path: &Path, sound: &Sound) -> std::io::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+
+    let bits_per_sample: u16 = match sound.format {
+        SampleFormat::U8 => 8,
+        SampleFormat::I16 => 16,
+    };
+    let block_align = sound.channels * bits_per_sample / 8;
+    let data_len = sound.data.len() as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&sound.channels.to_le_bytes())?;
+    writer.write_all(&sound.sample_rate.to_le_bytes())?;
+    writer.write_all(&(sound.sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    writer.write_all(&sound.data)?;
+    writer.flush()
+}
+
+fn convert_rle_data(vfs: &Vfs) {
+    for &(kind, short_kind, folder, list, sprite_type) in RLE_ENTRIES.iter() {
         println!("file: {:?}", &kind);
 
         // create a subfolder for the data if it doesn't exist
@@ -252,20 +353,22 @@ fn convert_rle_data() {
 
 
         // load the data from the list file
-        let list_path = Path::new(list);
-        let list = load_list_data(&list_path, use_v2).unwrap();
+        let list = load_list_data(vfs, list).unwrap();
 
         println!("list.items.len() == {:?}", list.items.len());
 
         // load the actual sprites into the database
-        let rle_paths = read_dir(folder).unwrap();
+        let rle_paths = vfs.list(folder);
         let mut resources = Vec::<Resource>::new();
 
-        for entry in rle_paths {
-            let entry = entry.unwrap();
-            let path = entry.path();
-
-            let res_file: ResourceFile = load_rle_data(&path).unwrap();
+        for path in rle_paths {
+            let res_file: ResourceFile = match load_rle_data(&path, sprite_type) {
+                Ok(res_file) => res_file,
+                Err(e) => {
+                    println!("{:?}: {}", path, e);
+                    continue
+                }
+            };
 
             for resource in res_file.resources {
                 resources.push(resource);
@@ -287,7 +390,7 @@ fn convert_rle_data() {
                                                     item.id);
                             let ent = RleCombiEntry {
                                 id: item.id,
-                                name: item.name.clone(),
+                                name: item.name.to_string(),
                                 x_offset: rle.offset_x,
                                 y_offset: rle.offset_y,
                                 width: rle.width,
@@ -369,14 +472,21 @@ fn load_rmm_data(path: &Path) -> Result<Map, Error> {
     parse_rmm(&bytes)
 }
 
-fn load_list_data(path: &Path, use_v2: bool) -> Result<List, Error> {
-    let mut file = File::open(path)?;
-    let mut bytes = Vec::<u8>::new();
-    file.read_to_end(&mut bytes)?;
-    parse_lst(&bytes, use_v2)
+fn load_list_data(vfs: &Vfs, path: &str) -> Result<List, Error> {
+    let bytes = vfs.read(path)?;
+    let (list, fallbacks) = parse_lst_lenient(&bytes)?;
+    for fallback in fallbacks {
+        println!("{:?}: {:?}", path, fallback);
+    }
+    Ok(list)
 }
 
-fn load_rle_data(path: &Path) -> Result<ResourceFile, Error> {
+fn load_rms_data(vfs: &Vfs, path: &str) -> Result<Sound, Error> {
+    let bytes = vfs.read(path)?;
+    parse_rms(&bytes)
+}
+
+fn load_rle_data(path: &Path, sprite_type: SpriteType) -> Result<ResourceFile, Error> {
     // open and read the file
     let mut file = File::open(path)?;
     let mut bytes = Vec::<u8>::new();
@@ -393,8 +503,14 @@ fn load_rle_data(path: &Path) -> Result<ResourceFile, Error> {
         }
     }
 
-    // parse && append results
-    parse_rle(file_num, &mut bytes)
+    // parse && append results, the damaged resources are left out; the keyed
+    // colour is exported as transparent in the sprite png's
+    let (res_file, fallbacks) = parse_rle_lenient_with(
+        file_num, &bytes, PixelFormat::Rgba8888, sprite_type.color_key())?;
+    for fallback in fallbacks {
+        println!("{:?}: {:?}", path, fallback);
+    }
+    Ok(res_file)
 }
 
 struct RleCombiEntry {
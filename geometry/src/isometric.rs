@@ -0,0 +1,209 @@
+//! Where a map tile is on the screen. The tiles are diamonds, the x axis of the map runs down
+//! to the right and the y axis down to the left, so tile (0, 0) is at the top of the map.
+//! Screen coordinates are relative to the map's origin, the top left of tile (0, 0)'s
+//! bounding box.
+
+use crate::point::Point;
+use crate::rectangle::Rectangle;
+
+/// The size of a map tile in the 3.9 client
+pub const TILE_WIDTH: i32 = 48;
+pub const TILE_HEIGHT: i32 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    tile_width: i32,
+    tile_height: i32,
+}
+
+impl Projection {
+    /// Both sides have to be positive and even, so the diamond's corners are on whole pixels
+    pub fn new(tile_width: i32, tile_height: i32) -> Projection {
+        assert!(tile_width > 0 && tile_width % 2 == 0, "invalid tile width {}", tile_width);
+        assert!(tile_height > 0 && tile_height % 2 == 0, "invalid tile height {}", tile_height);
+        Projection { tile_width, tile_height }
+    }
+
+    pub fn tile_width(&self) -> i32 {
+        self.tile_width
+    }
+
+    pub fn tile_height(&self) -> i32 {
+        self.tile_height
+    }
+
+    /// The top left of the tile's bounding box
+    pub fn tile_to_screen(&self, tile: &Point<i32>) -> Point<i32> {
+        Point::new(
+            (tile.x - tile.y) * self.half_width(),
+            (tile.x + tile.y) * self.half_height(),
+        )
+    }
+
+    /// The centre of the tile's diamond
+    pub fn tile_center(&self, tile: &Point<i32>) -> Point<i32> {
+        let origin = self.tile_to_screen(tile);
+        Point::new(origin.x + self.half_width(), origin.y + self.half_height())
+    }
+
+    /// The tile whose diamond holds the point. A point on the edge between two diamonds
+    /// belongs to the one further down the map, so every point is in exactly one tile.
+    pub fn screen_to_tile(&self, point: &Point<i32>) -> Point<i32> {
+        let (hw, hh) = (self.half_width(), self.half_height());
+        // scaled map coordinates, one tile is 2 * hw * hh long on each axis
+        let u = (point.x - hw) * hh;
+        let v = point.y * hw;
+        let len = 2 * hw * hh;
+        Point::new((v + u).div_euclid(len), (v - u).div_euclid(len))
+    }
+
+    /// Whether the point is in the tile's diamond, edges included
+    pub fn contains(&self, tile: &Point<i32>, point: &Point<i32>) -> bool {
+        let (hw, hh) = (self.half_width(), self.half_height());
+        let center = self.tile_center(tile);
+        let dx = (point.x - center.x).abs();
+        let dy = (point.y - center.y).abs();
+        dx * hh + dy * hw <= hw * hh
+    }
+
+    /// The tiles that are at least partly inside the viewport. The diamonds cover the view
+    /// at an angle, so the range also holds tiles outside of its corners; check each tile
+    /// with `contains` or the map's size where that matters.
+    pub fn visible_tiles(&self, viewport: &Rectangle<i32>) -> TileRange {
+        let left = viewport.location.x;
+        let top = viewport.location.y;
+        let right = left + viewport.size.width;
+        let bottom = top + viewport.size.height;
+        // the tile coordinates only grow or shrink along each screen axis, so the
+        // corners of the viewport hold the smallest and largest of them
+        let corners = [
+            self.screen_to_tile(&Point::new(left, top)),
+            self.screen_to_tile(&Point::new(right, top)),
+            self.screen_to_tile(&Point::new(left, bottom)),
+            self.screen_to_tile(&Point::new(right, bottom)),
+        ];
+        TileRange {
+            min: Point::new(
+                corners.iter().map(|tile| tile.x).min().unwrap(),
+                corners.iter().map(|tile| tile.y).min().unwrap(),
+            ),
+            max: Point::new(
+                corners.iter().map(|tile| tile.x).max().unwrap(),
+                corners.iter().map(|tile| tile.y).max().unwrap(),
+            ),
+        }
+    }
+
+    fn half_width(&self) -> i32 {
+        self.tile_width / 2
+    }
+
+    fn half_height(&self) -> i32 {
+        self.tile_height / 2
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Projection {
+        Projection::new(TILE_WIDTH, TILE_HEIGHT)
+    }
+}
+
+/// A range of tile coordinates, both ends included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileRange {
+    pub min: Point<i32>,
+    pub max: Point<i32>,
+}
+
+impl TileRange {
+    pub fn contains(&self, tile: &Point<i32>) -> bool {
+        tile.x >= self.min.x && tile.x <= self.max.x
+        && tile.y >= self.min.y && tile.y <= self.max.y
+    }
+
+    /// The part of the range that is on a map of `width` by `height` tiles
+    pub fn clamp(&self, width: i32, height: i32) -> TileRange {
+        TileRange {
+            min: Point::new(self.min.x.max(0), self.min.y.max(0)),
+            max: Point::new(self.max.x.min(width - 1), self.max.y.min(height - 1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_screen_round_trip() {
+        let projection = Projection::default();
+        assert_eq!(projection.tile_to_screen(&Point::new(0, 0)), Point::new(0, 0));
+        assert_eq!(projection.tile_to_screen(&Point::new(1, 0)), Point::new(24, 12));
+        assert_eq!(projection.tile_to_screen(&Point::new(0, 1)), Point::new(-24, 12));
+        assert_eq!(projection.tile_center(&Point::new(2, 1)), Point::new(48, 48));
+
+        for x in -5..6 {
+            for y in -5..6 {
+                let tile = Point::new(x, y);
+                let center = projection.tile_center(&tile);
+                assert_eq!(projection.screen_to_tile(&center), tile);
+                // every point of the bounding box is in this tile or one of its neighbours
+                let origin = projection.tile_to_screen(&tile);
+                for px in origin.x..origin.x + TILE_WIDTH {
+                    for py in origin.y..origin.y + TILE_HEIGHT {
+                        let point = Point::new(px, py);
+                        let picked = projection.screen_to_tile(&point);
+                        assert!(projection.contains(&picked, &point), "{:?} in {:?}", point, picked);
+                        assert!((picked.x - tile.x).abs() + (picked.y - tile.y).abs() <= 1);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_diamond_hit_test() {
+        let projection = Projection::default();
+        let tile = Point::new(0, 0);
+        // the corners of the diamond
+        assert!(projection.contains(&tile, &Point::new(24, 0)));
+        assert!(projection.contains(&tile, &Point::new(48, 12)));
+        assert!(projection.contains(&tile, &Point::new(24, 24)));
+        assert!(projection.contains(&tile, &Point::new(0, 12)));
+        // the corners of the bounding box are in the neighbours
+        assert!(!projection.contains(&tile, &Point::new(1, 1)));
+        assert!(!projection.contains(&tile, &Point::new(47, 23)));
+        assert_eq!(projection.screen_to_tile(&Point::new(1, 1)), Point::new(-1, 0));
+        assert_eq!(projection.screen_to_tile(&Point::new(47, 1)), Point::new(0, -1));
+        assert_eq!(projection.screen_to_tile(&Point::new(1, 23)), Point::new(0, 1));
+        assert_eq!(projection.screen_to_tile(&Point::new(47, 23)), Point::new(1, 0));
+    }
+
+    #[test]
+    fn test_visible_tiles() {
+        let projection = Projection::default();
+        let viewport = Rectangle::new_from_points((0, 0), (96, 48));
+        let range = projection.visible_tiles(&viewport);
+        assert_eq!(range, TileRange { min: Point::new(-1, -2), max: Point::new(3, 2) });
+
+        // every tile with a pixel in the viewport is in the range
+        for x in -10..10 {
+            for y in -10..10 {
+                let tile = Point::new(x, y);
+                let origin = projection.tile_to_screen(&tile);
+                let visible = (origin.x..origin.x + TILE_WIDTH).any(|px| {
+                    (origin.y..origin.y + TILE_HEIGHT).any(|py| {
+                        let point = Point::new(px, py);
+                        viewport.contains_point(&point) && projection.screen_to_tile(&point) == tile
+                    })
+                });
+                if visible {
+                    assert!(range.contains(&tile), "{:?}", tile);
+                }
+            }
+        }
+
+        assert_eq!(range.clamp(3, 3), TileRange { min: Point::new(0, 0), max: Point::new(2, 2) });
+    }
+}
@@ -1,3 +1,4 @@
+pub mod isometric;
 pub mod point;
 pub mod rectangle;
 pub mod size;